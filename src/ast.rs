@@ -1,15 +1,21 @@
-use std::fmt::Display;
+use std::{cell::Cell, rc::Rc};
 
-use crate::scanner::Token;
+use crate::scanner::{Span, Token};
 
 #[derive(Debug)]
 pub enum Expr {
     Binary(Binary),
-    Literal(Literal),
+    Literal(LiteralExpr),
     Unary(Unary),
     Grouping(Grouping),
     Variable(Variable),
     Assignment(Assignment),
+    Logical(Logical),
+    Call(Call),
+    Get(Get),
+    Set(Set),
+    This(This),
+    Super(Super),
 }
 
 impl Expr {
@@ -21,132 +27,176 @@ impl Expr {
             Expr::Grouping(grouping) => visitor.visit_grouping(grouping),
             Expr::Variable(variable) => visitor.visit_variable(variable),
             Expr::Assignment(assignment) => visitor.visit_assignment(assignment),
+            Expr::Logical(logical) => visitor.visit_logical(logical),
+            Expr::Call(call) => visitor.visit_call(call),
+            Expr::Get(get) => visitor.visit_get(get),
+            Expr::Set(set) => visitor.visit_set(set),
+            Expr::This(this) => visitor.visit_this(this),
+            Expr::Super(sup) => visitor.visit_super(sup),
         };
     }
+
+    // First assignment or call found, evaluating those changes the state
+    pub fn side_effect(&self) -> Option<Span> {
+        match self {
+            Expr::Assignment(_) | Expr::Call(_) | Expr::Set(_) => Some(self.span()),
+            Expr::Binary(Binary(left, _, right)) | Expr::Logical(Logical(left, _, right)) => {
+                left.side_effect().or_else(|| right.side_effect())
+            }
+            Expr::Unary(Unary(_, expr)) | Expr::Grouping(Grouping(expr, _)) => expr.side_effect(),
+            Expr::Get(get) => get.object.side_effect(),
+            Expr::Literal(_) | Expr::Variable(_) | Expr::This(_) | Expr::Super(_) => None,
+        }
+    }
+
+    // Source code covered by the whole expression
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary(Binary(left, _, right)) | Expr::Logical(Logical(left, _, right)) => {
+                left.span().to(right.span())
+            }
+            Expr::Literal(literal) => literal.span,
+            Expr::Unary(Unary(operator, right)) => operator.span.to(right.span()),
+            Expr::Grouping(Grouping(_, span)) => *span,
+            Expr::Variable(variable) => variable.name.span,
+            Expr::Assignment(assignment) => assignment.name.span.to(assignment.value.span()),
+            Expr::Call(call) => call.callee.span().to(call.paren.span),
+            Expr::Get(get) => get.object.span().to(get.name.span),
+            Expr::Set(set) => set.object.span().to(set.value.span()),
+            Expr::This(this) => this.keyword.span,
+            Expr::Super(sup) => sup.keyword.span.to(sup.method.span),
+        }
+    }
 }
 #[derive(Debug)]
 pub struct Assignment {
-    pub name: Token,
+    pub name: Token<'static>,
     pub value: Box<Expr>,
+    // Number of scopes between the assignment and the variable,
+    // filled by the resolver, `None` means it's a global.
+    pub depth: Cell<Option<usize>>,
 }
 impl Assignment {
-    pub fn new(name: Token, value: Box<Expr>) -> Self {
-        Self { name, value }
+    pub fn new(name: Token<'static>, value: Box<Expr>) -> Self {
+        Self {
+            name,
+            value,
+            depth: Cell::new(None),
+        }
     }
 }
 
 #[derive(Debug)]
-pub struct Binary(pub Box<Expr>, pub Token, pub Box<Expr>);
+pub struct Binary(pub Box<Expr>, pub Token<'static>, pub Box<Expr>);
 
+// Kept apart from `Binary` because the right operand may never be evaluated
 #[derive(Debug)]
-pub struct Variable {
-    pub name: Token,
+pub struct Logical(pub Box<Expr>, pub Token<'static>, pub Box<Expr>);
+
+#[derive(Debug)]
+pub struct Call {
+    pub callee: Box<Expr>,
+    // Closing parenthesis, used to report errors on the call location
+    pub paren: Token<'static>,
+    pub arguments: Vec<Box<Expr>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Literal {
-    Num(f64),
-    Str(String),
-    Bool(bool),
-    Nil,
+// Property access `object.name`
+#[derive(Debug)]
+pub struct Get {
+    pub object: Box<Expr>,
+    pub name: Token<'static>,
 }
 
-impl Literal {
-    pub fn is_truthy(&self) -> bool {
-        match self {
-            Literal::Num(_) => true,
-            Literal::Str(_) => true,
-            Literal::Bool(value) => *value,
-            Literal::Nil => false,
-        }
-    }
+// Property assignment `object.name = value`
+#[derive(Debug)]
+pub struct Set {
+    pub object: Box<Expr>,
+    pub name: Token<'static>,
+    pub value: Box<Expr>,
+}
 
-    pub fn try_num(&self) -> Result<f64, String> {
-        match self {
-            Literal::Num(num) => Ok(*num),
-            _ => Err(format!("`{}` NaN", self)),
+#[derive(Debug)]
+pub struct This {
+    pub keyword: Token<'static>,
+    // Filled by the resolver, see `Assignment::depth`
+    pub depth: Cell<Option<usize>>,
+}
+impl This {
+    pub fn new(keyword: Token<'static>) -> Self {
+        Self {
+            keyword,
+            depth: Cell::new(None),
         }
     }
+}
 
-    pub fn try_string(&self) -> Result<String, String> {
-        match self {
-            Literal::Str(value) => Ok(value.to_string()),
-            _ => Err(format!("`{}` Not a String", self)),
+// `super.method`, always followed by a method name
+#[derive(Debug)]
+pub struct Super {
+    pub keyword: Token<'static>,
+    pub method: Token<'static>,
+    // Filled by the resolver, see `Assignment::depth`
+    pub depth: Cell<Option<usize>>,
+}
+impl Super {
+    pub fn new(keyword: Token<'static>, method: Token<'static>) -> Self {
+        Self {
+            keyword,
+            method,
+            depth: Cell::new(None),
         }
     }
 }
 
-impl Display for Literal {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Literal::Num(val) => write!(f, "{}", val),
-            Literal::Str(val) => write!(f, "{}", val),
-            Literal::Bool(val) => write!(f, "{}", val),
-            Literal::Nil => write!(f, "nil"),
+#[derive(Debug)]
+pub struct Variable {
+    pub name: Token<'static>,
+    // Filled by the resolver, see `Assignment::depth`
+    pub depth: Cell<Option<usize>>,
+}
+impl Variable {
+    pub fn new(name: Token<'static>) -> Self {
+        Self {
+            name,
+            depth: Cell::new(None),
         }
     }
 }
 
 #[derive(Debug)]
-pub struct Unary(pub Token, pub Box<Expr>);
+pub struct LiteralExpr {
+    pub value: Literal,
+    pub span: Span,
+}
+
+// Constant written in the source, evaluated to a `Value`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Num(f64),
+    Str(Rc<str>),
+    Bool(bool),
+    Nil,
+}
+
+#[derive(Debug)]
+pub struct Unary(pub Token<'static>, pub Box<Expr>);
 
+// The span includes the parentheses
 #[derive(Debug)]
-pub struct Grouping(pub Box<Expr>);
+pub struct Grouping(pub Box<Expr>, pub Span);
 
 pub trait VisitExpr<T> {
     fn visit_binary(&self, expr: &Binary) -> T;
-    fn visit_literal(&self, expr: &Literal) -> T;
+    fn visit_literal(&self, expr: &LiteralExpr) -> T;
     fn visit_unary(&self, expr: &Unary) -> T;
     fn visit_grouping(&self, expr: &Grouping) -> T;
     fn visit_variable(&self, expr: &Variable) -> T;
     fn visit_assignment(&self, expr: &Assignment) -> T;
-}
-
-struct AstPrinter;
-impl AstPrinter {
-    fn parenthesize(&self, name: String, exprs: &[&Expr]) -> String {
-        let r: String = exprs
-            .iter()
-            .map(|e| e.accept(self))
-            .collect::<Vec<String>>()
-            .join(" ");
-
-        return format!("({name} {r})");
-    }
-}
-
-impl VisitExpr<String> for AstPrinter {
-    fn visit_binary(&self, expr: &Binary) -> String {
-        self.parenthesize(
-            expr.1.lexeme.to_owned(),
-            &[expr.0.as_ref(), expr.2.as_ref()],
-        )
-    }
-
-    fn visit_literal(&self, expr: &Literal) -> String {
-        match expr {
-            Literal::Num(n) => n.to_string(),
-            Literal::Str(s) => s.to_owned(),
-            Literal::Bool(b) => format!("{b}"),
-            Literal::Nil => "nil".into(),
-        }
-    }
-
-    fn visit_unary(&self, expr: &Unary) -> String {
-        self.parenthesize(expr.0.lexeme.to_owned(), &[expr.1.as_ref()])
-    }
-
-    fn visit_grouping(&self, expr: &Grouping) -> String {
-        self.parenthesize("group".to_string(), &[expr.0.as_ref()])
-    }
-
-    fn visit_variable(&self, _: &Variable) -> String {
-        // AST Printer is no longer that's why we leave it to todo
-        todo!("visit variable not implemented")
-    }
-
-    fn visit_assignment(&self, _: &Assignment) -> String {
-        // AST Printer is no longer that's why we leave it to todo
-        todo!()
-    }
+    fn visit_logical(&self, expr: &Logical) -> T;
+    fn visit_call(&self, expr: &Call) -> T;
+    fn visit_get(&self, expr: &Get) -> T;
+    fn visit_set(&self, expr: &Set) -> T;
+    fn visit_this(&self, expr: &This) -> T;
+    fn visit_super(&self, expr: &Super) -> T;
 }
@@ -0,0 +1,89 @@
+use std::{fmt, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{
+    class::LoxInstance, env::Environment, intern::Symbol, interpreter::Interpreter,
+    statement::Function, value::Value,
+};
+
+// Anything that can be invoked with `callee(arguments)`
+pub trait LoxCallable: fmt::Display + fmt::Debug {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value>;
+
+    // Scope captured by the callable, see `Environment::release`
+    fn closure(&self) -> Option<&Environment> {
+        None
+    }
+}
+
+// Runtime representation of a `fun` declaration, `closure` is the
+// environment active when the function was declared. It shares its
+// bindings with that scope so captured variables stay alive and mutable.
+pub struct LoxFunction {
+    declaration: Rc<Function>,
+    closure: Environment,
+    // `init` methods always return the instance being constructed
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(declaration: Rc<Function>, closure: Environment, is_initializer: bool) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    // Creates a copy of the method whose closure has `this` bound to `instance`
+    pub fn bind(&self, instance: Rc<LoxInstance>) -> LoxFunction {
+        let environment = Environment::with_enclosing(self.closure.clone());
+        environment.define(Symbol::THIS, Value::Instance(instance));
+        LoxFunction::new(self.declaration.clone(), environment, self.is_initializer)
+    }
+
+    fn this(&self) -> Value {
+        self.closure
+            .get_local(Symbol::THIS)
+            .expect("Initializers are always bound to an instance")
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        let environment = Environment::with_enclosing(self.closure.clone());
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(param.symbol(), argument);
+        }
+        let value = interpreter.execute_block(&self.declaration.body, environment)?;
+        if self.is_initializer {
+            return Ok(self.this());
+        }
+        return Ok(value.unwrap_or(Value::Nil));
+    }
+
+    fn closure(&self) -> Option<&Environment> {
+        Some(&self.closure)
+    }
+}
+
+// The closure can reference the function itself, printing it would recurse
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.declaration.name.lexeme)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
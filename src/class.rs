@@ -0,0 +1,121 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use anyhow::Result;
+
+use crate::{
+    callable::{LoxCallable, LoxFunction},
+    intern::Symbol,
+    interpreter::{Interpreter, RuntimeError},
+    scanner::Token,
+    value::Value,
+};
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<Symbol, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<Symbol, Rc<LoxFunction>>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn superclass(&self) -> Option<&Rc<LoxClass>> {
+        self.superclass.as_ref()
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = &Rc<LoxFunction>> {
+        self.methods.values()
+    }
+
+    // Walks up the superclass chain when the method is not found
+    pub fn find_method(&self, name: Symbol) -> Option<Rc<LoxFunction>> {
+        self.methods.get(&name).cloned().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+}
+
+// Implemented on the `Rc` because every instance keeps a pointer to its class
+impl LoxCallable for Rc<LoxClass> {
+    fn arity(&self) -> usize {
+        self.find_method(Symbol::INIT)
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        let instance = Rc::new(LoxInstance::new(self.clone()));
+        if let Some(initializer) = self.find_method(Symbol::INIT) {
+            initializer
+                .bind(instance.clone())
+                .call(interpreter, arguments)?;
+        }
+        return Ok(Value::Instance(instance));
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: RefCell<HashMap<Symbol, Value>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // Fields shadow methods, methods are bound to the instance on access
+    pub fn get(self: &Rc<Self>, name: &Token) -> Result<Value> {
+        if let Some(value) = self.fields.borrow().get(&name.symbol()) {
+            return Ok(value.clone());
+        }
+        if let Some(method) = self.class.find_method(name.symbol()) {
+            return Ok(Value::Callable(Rc::new(method.bind(self.clone()))));
+        }
+        Err(RuntimeError(
+            Some(name.span),
+            format!("Undefined property '{}'.", name.lexeme),
+        )
+        .into())
+    }
+
+    pub fn set(&self, name: Symbol, value: Value) {
+        self.fields.borrow_mut().insert(name, value);
+    }
+}
+
+// Fields can reference the instance itself, printing them would recurse
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LoxInstance")
+            .field("class", &self.class.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -0,0 +1,191 @@
+use std::{cell::RefCell, collections::HashMap, error, fmt, hash::BuildHasher, rc::Rc};
+
+use crate::value::Value;
+
+/// A Lox value didn't have the type a Rust value needed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub found: String,
+}
+
+impl ConversionError {
+    fn new(expected: &'static str, value: &Value) -> Self {
+        Self {
+            expected,
+            found: value.type_name().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {} but got {}", self.expected, self.found)
+    }
+}
+impl error::Error for ConversionError {}
+
+/// Rust values that can be turned into a Lox value.
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+/// Rust values that can be read out of a Lox value.
+pub trait FromLox: Sized {
+    /// # Errors
+    ///
+    /// Fails when `value` doesn't have the type `Self` needs.
+    fn from_lox(value: Value) -> Result<Self, ConversionError>;
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl FromLox for Value {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        Ok(value)
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Value {
+        Value::Num(self)
+    }
+}
+
+impl FromLox for f64 {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Num(num) => Ok(num),
+            _ => Err(ConversionError::new("number", &value)),
+        }
+    }
+}
+
+// Lox only has doubles, integers must be whole and in range to come back
+macro_rules! integer {
+    ($($int:ty),*) => {$(
+        impl IntoLox for $int {
+            #[allow(clippy::cast_precision_loss, clippy::cast_lossless)]
+            fn into_lox(self) -> Value {
+                Value::Num(self as f64)
+            }
+        }
+
+        impl FromLox for $int {
+            #[allow(clippy::cast_possible_truncation)]
+            fn from_lox(value: Value) -> Result<Self, ConversionError> {
+                let num = f64::from_lox(value)?;
+                // The cast saturates, every integer type fits in an `i128`
+                let int = (num.fract() == 0.0).then(|| <$int>::try_from(num as i128).ok());
+                int.flatten().ok_or_else(|| ConversionError {
+                    expected: stringify!($int),
+                    found: num.to_string(),
+                })
+            }
+        }
+    )*};
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromLox for bool {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Bool(value) => Ok(value),
+            _ => Err(ConversionError::new("boolean", &value)),
+        }
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::Str(self.into())
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::Str(self.into())
+    }
+}
+
+impl FromLox for String {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Str(value) => Ok(value.as_str().to_string()),
+            _ => Err(ConversionError::new("string", &value)),
+        }
+    }
+}
+
+// `nil` is `None`
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        self.map_or(Value::Nil, IntoLox::into_lox)
+    }
+}
+
+impl<T: FromLox> FromLox for Option<T> {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::from_lox(value).map(Some),
+        }
+    }
+}
+
+// Lists and maps are shared, converting them back copies their elements
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        let values = self.into_iter().map(IntoLox::into_lox).collect();
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+}
+
+impl<T: FromLox> FromLox for Vec<T> {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::List(values) => values.borrow().iter().cloned().map(T::from_lox).collect(),
+            _ => Err(ConversionError::new("list", &value)),
+        }
+    }
+}
+
+impl<T: IntoLox, S> IntoLox for HashMap<String, T, S> {
+    fn into_lox(self) -> Value {
+        let entries = self
+            .into_iter()
+            .map(|(key, value)| (key, value.into_lox()))
+            .collect();
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+}
+
+impl<T: FromLox, S: BuildHasher + Default> FromLox for HashMap<String, T, S> {
+    fn from_lox(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Map(entries) => entries
+                .borrow()
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), T::from_lox(value.clone())?)))
+                .collect(),
+            _ => Err(ConversionError::new("map", &value)),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::{fmt::Write, io};
+
+use crate::{
+    error::Error,
+    interpreter::RuntimeError,
+    parser::ParserError,
+    scanner::{LexError, Span},
+    sink::Sink,
+};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Error report pointing at a piece of the source code, rendered like
+//
+//   error: Operands must be numbers.
+//    --> script.lox:3:7
+//     |
+//   3 | print 1 - "a";
+//     |       ^^^^^^^
+//     = help: ...
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub title: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub fn new(title: &'static str, message: String, span: Span) -> Self {
+        Self {
+            title,
+            message,
+            span,
+            help: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    // Parse and resolver errors are reported all together, errors that don't know
+    // their location have no diagnostic
+    pub fn from_error(err: &Error) -> Vec<Self> {
+        match err {
+            Error::Syntax(errors) => {
+                let lex_errors = errors.lex_errors.iter().map(Self::from);
+                let parse_errors = errors.parse_errors.iter().map(Self::from);
+                lex_errors.chain(parse_errors).collect()
+            }
+            Error::Resolve(errors) => errors
+                .iter()
+                .map(|err| Self::new("error", err.message.clone(), err.span))
+                .collect(),
+            Error::Runtime(RuntimeError(Some(span), message)) => {
+                vec![Self::new("runtime error", message.clone(), *span)]
+            }
+            Error::Runtime(RuntimeError(None, _)) | Error::Io(_) => vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn render(&self, filename: &str, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("{style}{text}{RESET}")
+            } else {
+                text.to_string()
+            }
+        };
+        let line_number = self.span.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let bar = paint(BLUE, "|");
+
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "{}{}",
+            paint(RED, self.title),
+            paint(BOLD, &format!(": {}", self.message))
+        );
+        let _ = writeln!(
+            report,
+            "{gutter}{} {filename}:{}",
+            paint(BLUE, "-->"),
+            self.span
+        );
+        if let Some(line) = source_line(source, self.span) {
+            // Spans crossing lines are only underlined until the end of the first one
+            let start = self.span.column - 1;
+            let underlined = source
+                .get(self.span.offset..self.span.offset + self.span.len)
+                .and_then(|text| text.lines().next())
+                .unwrap_or_default();
+            let width = underlined.chars().count().max(1);
+            let _ = writeln!(report, "{gutter} {bar}");
+            let _ = writeln!(report, "{} {bar} {line}", paint(BLUE, &line_number));
+            let _ = writeln!(
+                report,
+                "{gutter} {bar} {}{}",
+                " ".repeat(start),
+                paint(RED, &"^".repeat(width))
+            );
+        }
+        if let Some(help) = &self.help {
+            let _ = writeln!(report, "{gutter} {} help: {help}", paint(BLUE, "="));
+        }
+        report
+    }
+
+    // Writes the report to `sink`, coloured when it's a terminal
+    pub fn emit(&self, filename: &str, source: &str, sink: &mut Sink) -> io::Result<()> {
+        let color = sink.is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        sink.write(&self.render(filename, source, color))
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(err: &LexError) -> Self {
+        Self::new("error", err.message(), err.span)
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(err: &ParserError) -> Self {
+        Self::new("error", err.message.clone(), err.span)
+    }
+}
+
+// Writes the error as diagnostics when its location is known
+pub fn report(err: &Error, filename: &str, source: &str, sink: &mut Sink) -> io::Result<()> {
+    let diagnostics = Diagnostic::from_error(err);
+    if diagnostics.is_empty() {
+        let label = match err {
+            Error::Runtime(_) => "runtime error",
+            _ => "error",
+        };
+        sink.write(&format!("{label}: {err}\n"))?;
+    }
+    for mut diagnostic in diagnostics {
+        if diagnostic.span.offset >= source.trim_end().len() {
+            diagnostic = diagnostic.with_help("the source ended before the statement was complete");
+        }
+        diagnostic.emit(filename, source, sink)?;
+    }
+    Ok(())
+}
+
+fn source_line(source: &str, span: Span) -> Option<&str> {
+    source.lines().nth(span.line.checked_sub(1)?)
+}
@@ -1,35 +1,281 @@
-use crate::{ast::Literal, interpreter::RuntimeError, scanner::Token};
+use crate::{
+    callable::{LoxCallable, LoxFunction},
+    class::LoxClass,
+    intern::{Interner, Symbol},
+    interpreter::RuntimeError,
+    scanner::Token,
+    value::Value,
+};
 use anyhow::Result;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    rc::Rc,
+};
 
+type Bindings = Rc<RefCell<HashMap<Symbol, Value>>>;
+
+// Cloning an `Environment` is cheap and shares the same bindings,
+// the `enclosing` pointer links each scope with its parent.
+#[derive(Clone, Default)]
 pub struct Environment {
-    values: Rc<RefCell<HashMap<String, Literal>>>,
+    values: Bindings,
+    enclosing: Option<Rc<Environment>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Environment) -> Self {
         Self {
             values: Rc::new(RefCell::new(HashMap::new())),
+            enclosing: Some(Rc::new(enclosing)),
+        }
+    }
+
+    pub fn define(&self, name: Symbol, value: Value) {
+        self.values.borrow_mut().insert(name, value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value> {
+        if let Some(value) = self.values.borrow().get(&name.symbol()) {
+            return Ok(value.clone());
+        }
+        // Walk outwards until a scope knows the variable
+        match &self.enclosing {
+            Some(enclosing) => enclosing.get(name),
+            None => Err(RuntimeError(
+                Some(name.span),
+                format!("Undefined variable '{}'.", name.lexeme),
+            )
+            .into()),
+        }
+    }
+
+    pub fn assign(&self, name: &Token, value: Value) -> Result<()> {
+        if let Some(slot) = self.values.borrow_mut().get_mut(&name.symbol()) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.assign(name, value),
+            None => Err(RuntimeError(
+                Some(name.span),
+                format!("Undefined variable '{}'.", name.lexeme),
+            )
+            .into()),
+        }
+    }
+
+    // Only looks at this scope, without walking outwards
+    pub fn get_local(&self, name: Symbol) -> Option<Value> {
+        self.values.borrow().get(&name).cloned()
+    }
+
+    // Bindings of this scope sorted by name
+    pub fn bindings(&self, interner: &Interner) -> Vec<(String, Value)> {
+        let mut bindings: Vec<_> = self
+            .values
+            .borrow()
+            .iter()
+            .map(|(name, value)| (interner.resolve(*name).to_string(), value.clone()))
+            .collect();
+        bindings.sort_by(|(left, _), (right, _)| left.cmp(right));
+        bindings
+    }
+
+    // Used once the resolver knows in which scope the variable lives
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value> {
+        self.ancestor(distance).get(name)
+    }
+
+    pub fn assign_at(&self, distance: usize, name: &Token, value: Value) -> Result<()> {
+        self.ancestor(distance).assign(name, value)
+    }
+
+    pub fn ancestor(&self, distance: usize) -> &Environment {
+        let mut environment = self;
+        for _ in 0..distance {
+            environment = environment
+                .enclosing
+                .as_ref()
+                .expect("Resolver computed a depth deeper than the environment chain");
         }
+        environment
     }
 
-    pub fn define(&self, name: &Token, value: Literal) {
-        let envmap = &*self.values.clone();
-        envmap.borrow_mut().insert(name.lexeme.to_string(), value);
+    // Called when the scope is exited with the last handle to it. Functions
+    // and classes declared in a scope capture it, so the scope owns them and
+    // they own the scope back. The scope is garbage when every reference to
+    // it comes from that cycle: counting the references found by walking
+    // from the scope and comparing them with the strong counts tells the
+    // two apart, clearing the bindings then breaks the cycle. Instances,
+    // lists and maps are not walked, a function stored in a field or an
+    // element counts as used from outside and keeps the scope alive forever.
+    pub fn release(self) {
+        let has_closures = self
+            .values
+            .borrow()
+            .values()
+            .any(|value| matches!(value, Value::Callable(_) | Value::Class(_)));
+        if !has_closures {
+            return;
+        }
+        if Graph::new(&self).is_garbage() {
+            // Dropped outside the borrow, the values can own other scopes
+            let values = std::mem::take(&mut *self.values.borrow_mut());
+            drop(values);
+        }
+    }
+}
+
+// Reference counted values reachable from a scope being released
+enum Node {
+    Bindings(Bindings),
+    Enclosing(Rc<Environment>),
+    Callable(Rc<dyn LoxCallable>),
+    Method(Rc<LoxFunction>),
+    Class(Rc<LoxClass>),
+}
+
+struct Counts {
+    strong: usize,
+    // References found while walking the graph
+    internal: usize,
+    children: Vec<*const ()>,
+}
+
+struct Graph {
+    root: *const (),
+    counts: HashMap<*const (), Counts>,
+    pending: Vec<(*const (), Node)>,
+    // The scopes around the released one are alive, they are not walked
+    ancestors: HashSet<*const ()>,
+}
+
+impl Graph {
+    fn new(environment: &Environment) -> Self {
+        let mut ancestors = HashSet::new();
+        let mut enclosing = environment.enclosing.as_deref();
+        while let Some(scope) = enclosing {
+            ancestors.insert(Rc::as_ptr(&scope.values).cast::<()>());
+            enclosing = scope.enclosing.as_deref();
+        }
+        let root = Rc::as_ptr(&environment.values).cast::<()>();
+        let mut graph = Self {
+            root,
+            counts: HashMap::new(),
+            pending: Vec::new(),
+            ancestors,
+        };
+        // The handle being released accounts for one reference
+        graph.counts.insert(
+            root,
+            Counts {
+                strong: Rc::strong_count(&environment.values),
+                internal: 1,
+                children: Vec::new(),
+            },
+        );
+        graph.walk(root, Node::Bindings(environment.values.clone()));
+        while let Some((id, node)) = graph.pending.pop() {
+            graph.walk(id, node);
+        }
+        graph
+    }
+
+    fn walk(&mut self, id: *const (), node: Node) {
+        match node {
+            Node::Bindings(values) => {
+                for value in values.borrow().values() {
+                    match value {
+                        Value::Callable(callable) => self.reference(id, callable, Node::Callable),
+                        Value::Class(class) => self.reference(id, class, Node::Class),
+                        _ => {}
+                    }
+                }
+            }
+            Node::Enclosing(environment) => self.environment(id, &environment),
+            Node::Callable(callable) => {
+                if let Some(closure) = callable.closure() {
+                    self.environment(id, closure);
+                }
+            }
+            Node::Method(method) => {
+                if let Some(closure) = method.closure() {
+                    self.environment(id, closure);
+                }
+            }
+            Node::Class(class) => {
+                if let Some(superclass) = class.superclass() {
+                    self.reference(id, superclass, Node::Class);
+                }
+                for method in class.methods() {
+                    self.reference(id, method, Node::Method);
+                }
+            }
+        }
+    }
+
+    fn environment(&mut self, id: *const (), environment: &Environment) {
+        if !self.is_ancestor(&environment.values) {
+            self.reference(id, &environment.values, Node::Bindings);
+        }
+        if let Some(enclosing) = &environment.enclosing {
+            if !self.is_ancestor(&enclosing.values) {
+                self.reference(id, enclosing, Node::Enclosing);
+            }
+        }
+    }
+
+    fn is_ancestor(&self, values: &Bindings) -> bool {
+        self.ancestors.contains(&Rc::as_ptr(values).cast::<()>())
     }
 
-    pub fn get(&self, name: &Token) -> Result<Literal> {
-        (*self.values.clone().borrow())
-            .get(&name.lexeme)
-            .and_then(|v| Some(v.clone()))
-            .ok_or_else(|| {
-                RuntimeError(name.line, format!("Undefined variable {}", name.lexeme)).into()
-            })
+    // Records a reference from `from`, the strong count is read before the
+    // graph takes its own clone of the value
+    fn reference<T: ?Sized>(&mut self, from: *const (), to: &Rc<T>, node: fn(Rc<T>) -> Node) {
+        let id = Rc::as_ptr(to).cast::<()>();
+        self.counts
+            .get_mut(&from)
+            .expect("Nodes are counted before being walked")
+            .children
+            .push(id);
+        match self.counts.entry(id) {
+            Entry::Occupied(mut entry) => entry.get_mut().internal += 1,
+            Entry::Vacant(entry) => {
+                entry.insert(Counts {
+                    strong: Rc::strong_count(to),
+                    internal: 1,
+                    children: Vec::new(),
+                });
+                self.pending.push((id, node(to.clone())));
+            }
+        }
     }
 
-    pub fn assign(&self, name: &Token, value: Literal) -> Result<()>{
-        let _ = self.get(name)?; // check if it exists first
-        self.define(name, value); // Set value
-        return Ok(())
+    // Anything referenced from outside the graph is alive, and so is
+    // everything it reaches
+    fn is_garbage(&self) -> bool {
+        let mut alive: Vec<_> = self
+            .counts
+            .iter()
+            .filter(|(_, counts)| counts.strong > counts.internal)
+            .map(|(id, _)| *id)
+            .collect();
+        let mut seen: HashSet<_> = alive.iter().copied().collect();
+        while let Some(id) = alive.pop() {
+            if id == self.root {
+                return false;
+            }
+            for child in &self.counts[&id].children {
+                if seen.insert(*child) {
+                    alive.push(*child);
+                }
+            }
+        }
+        true
     }
 }
@@ -0,0 +1,80 @@
+use std::{error, fmt, io};
+
+use crate::{interpreter::RuntimeError, parser::SyntaxErrors, resolver::ResolverError};
+
+/// Anything that can go wrong while running Lox code.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The script couldn't be read.
+    Io(io::Error),
+    /// Scanning or parsing failed, every error found is kept.
+    Syntax(SyntaxErrors),
+    /// The program parsed but is invalid, e.g. `return` at the top level,
+    /// every error found is kept.
+    Resolve(Vec<ResolverError>),
+    /// The program failed while running.
+    Runtime(RuntimeError),
+}
+
+impl Error {
+    /// Exit code the CLI uses for this error, following `sysexits.h`.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io(_) => 74,
+            Error::Syntax(_) | Error::Resolve(_) => 65,
+            Error::Runtime(_) => 70,
+        }
+    }
+
+    // Errors inside the interpreter are carried by `anyhow`, natives may
+    // fail with any error so those have no location
+    pub(crate) fn from_runtime(err: anyhow::Error) -> Self {
+        match err.downcast::<RuntimeError>() {
+            Ok(err) => Error::Runtime(err),
+            Err(err) => Error::Runtime(RuntimeError(None, err.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Syntax(err) => write!(f, "{err}"),
+            Error::Resolve(errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            Error::Runtime(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+// Displays the wrapped error as is, so it's not its source either
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<SyntaxErrors> for Error {
+    fn from(err: SyntaxErrors) -> Self {
+        Error::Syntax(err)
+    }
+}
+
+impl From<Vec<ResolverError>> for Error {
+    fn from(errors: Vec<ResolverError>) -> Self {
+        Error::Resolve(errors)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Self {
+        Error::Runtime(err)
+    }
+}
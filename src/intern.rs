@@ -0,0 +1,59 @@
+use std::{collections::HashMap, rc::Rc};
+
+// Looked up by the interpreter itself, interned first so they are constants
+const PREDEFINED: [&str; 3] = ["this", "super", "init"];
+
+// Identifier or string constant of the program, equal symbols are equal
+// strings so comparing and hashing them is comparing and hashing an index.
+// The index is into the table of the session that interned it, symbols of
+// two sessions can't be compared.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub const THIS: Symbol = Symbol(0);
+    pub const SUPER: Symbol = Symbol(1);
+    pub const INIT: Symbol = Symbol(2);
+}
+
+// Owned by the interpreter, the strings are freed with the session. Only
+// names and constants the parser keeps and the names of the globals the
+// host defines are interned, values built at runtime never are.
+pub struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        let mut interner = Self {
+            symbols: HashMap::new(),
+            strings: vec![],
+        };
+        for text in PREDEFINED {
+            interner.intern(text);
+        }
+        interner
+    }
+
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return *symbol;
+        }
+        let index = u32::try_from(self.strings.len()).expect("Too many symbols");
+        let symbol = Symbol(index);
+        let text: Rc<str> = text.into();
+        self.symbols.insert(text.clone(), symbol);
+        self.strings.push(text);
+        symbol
+    }
+
+    // Doesn't add `text` to the table, for names that only need reading
+    pub fn lookup(&self, text: &str) -> Option<Symbol> {
+        self.symbols.get(text).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &Rc<str> {
+        &self.strings[symbol.0 as usize]
+    }
+}
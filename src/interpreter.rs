@@ -1,165 +1,480 @@
-use std::{error, fmt};
+use std::{
+    cell::{Cell, RefCell},
+    error, fmt, io,
+    rc::Rc,
+};
 
 use anyhow::Result;
 
 use crate::{
-    ast::{Binary, Expr, Grouping, Literal, Unary, VisitExpr},
+    ast::{
+        Binary, Call, Expr, Get, Grouping, LiteralExpr, Logical, Set, Super, This, Unary, Variable,
+        VisitExpr,
+    },
+    callable::{LoxCallable, LoxFunction},
+    class::LoxClass,
     env::Environment,
-    scanner::TokenType,
-    statement::{Stmt, VisitStmt},
+    intern::{Interner, Symbol},
+    native::{standard_library, NativeFunction},
+    scanner::{Span, TokenType},
+    sink::Sink,
+    statement::{Block, Class, Function, If, Return, Stmt, VisitStmt, While},
+    value::Value,
 };
 
 #[derive(Debug)]
-pub struct RuntimeError(pub usize, pub String);
+// Errors raised by the host, outside of any script, have no location
+pub struct RuntimeError(pub Option<Span>, pub String);
 
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "RuntimeError:\n\n\t[Line {}] {}", self.0, self.1)
+        match self.0 {
+            Some(span) => write!(f, "{span}: {}", self.1),
+            None => write!(f, "{}", self.1),
+        }
     }
 }
 impl error::Error for RuntimeError {}
 
+// A Lox call takes up to about 16 KiB of Rust stack in debug builds and
+// 4 KiB in release builds, measured with statements nested in each call
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+fn operands_error(span: Span) -> RuntimeError {
+    RuntimeError(Some(span), "Operands must be numbers.".into())
+}
+
 // type RuntimeResult<T> = Result<T, RuntimeError>;
+//
+// Executing a statement yields `Some(value)` while a `return` is
+// unwinding towards the function call that started it.
 pub struct Interpreter {
-    environment: Environment,
+    globals: Environment,
+    environment: RefCell<Environment>,
+    interner: RefCell<Interner>,
+    // Destination of `print`
+    output: RefCell<Sink>,
+    // Calls currently running, and how many can be before a stack overflow
+    depth: Cell<usize>,
+    max_depth: Cell<usize>,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
-            environment: Environment::new(),
+        let globals = Environment::new();
+        let interpreter = Self {
+            environment: RefCell::new(globals.clone()),
+            globals,
+            interner: RefCell::new(Interner::new()),
+            output: RefCell::new(Sink::Stdout),
+            depth: Cell::new(0),
+            max_depth: Cell::new(DEFAULT_MAX_CALL_DEPTH),
+        };
+        for native in standard_library() {
+            interpreter.define_native(native);
         }
+        interpreter
+    }
+
+    // Makes a Rust function callable from Lox as a global
+    pub fn register_native(
+        &self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&Interpreter, Vec<Value>) -> Result<Value> + 'static,
+    ) {
+        self.define_native(NativeFunction::new(name, arity, function));
+    }
+
+    pub fn set_max_call_depth(&self, depth: usize) {
+        self.max_depth.set(depth);
+    }
+
+    pub fn set_output(&self, output: Sink) {
+        self.output.replace(output);
+    }
+
+    pub fn write_output(&self, text: &str) -> io::Result<()> {
+        self.output.borrow_mut().write(text)
+    }
+
+    pub fn globals(&self) -> &Environment {
+        &self.globals
     }
-    fn evaluate(&self, expr: &Box<Expr>) -> Result<Literal> {
+
+    pub fn interner(&self) -> &RefCell<Interner> {
+        &self.interner
+    }
+
+    pub fn define_native(&self, native: NativeFunction) {
+        let name = self.interner.borrow_mut().intern(native.name());
+        self.globals.define(name, Value::Callable(Rc::new(native)));
+    }
+    fn evaluate(&self, expr: &Expr) -> Result<Value> {
         return expr.accept(self);
     }
 
-    fn execute(&self, stmt: &Box<Stmt>) -> Result<Option<Literal>> {
+    fn execute(&self, stmt: &Stmt) -> Result<Option<Value>> {
         let ex = stmt.accept(self)?;
         return Ok(ex);
     }
 
-    pub fn interpret(&self, statements: Vec<Box<Stmt>>) -> Result<Option<Literal>> {
-        for stmt in statements {
-            self.execute(&stmt)?;
+    // Runs the statements inside `environment`, the previous environment
+    // is restored afterwards even if one of the statements fails.
+    pub fn execute_block(
+        &self,
+        stmts: &[Box<Stmt>],
+        environment: Environment,
+    ) -> Result<Option<Value>> {
+        let previous = self.environment.replace(environment);
+        let result = self.execute_all(stmts);
+        self.environment.replace(previous).release();
+        return result;
+    }
+
+    // Stops at the first `return`
+    fn execute_all(&self, stmts: &[Box<Stmt>]) -> Result<Option<Value>> {
+        for stmt in stmts {
+            if let Some(value) = self.execute(stmt)? {
+                return Ok(Some(value));
+            }
         }
         return Ok(None);
     }
+
+    // Returns the value of the last statement when it's an expression,
+    // so the REPL can echo it
+    pub fn interpret(&self, statements: Vec<Box<Stmt>>) -> Result<Option<Value>> {
+        let mut value = None;
+        for stmt in statements {
+            value = if let Stmt::Expr(expr) = stmt.as_ref() {
+                Some(self.evaluate(expr)?)
+            } else {
+                self.execute(&stmt)?;
+                None
+            };
+        }
+        return Ok(value);
+    }
 }
 
-impl VisitStmt<Result<Option<Literal>>> for Interpreter {
-    fn visit_expr_stmt(&self, stmt: &Box<Expr>) -> Result<Option<Literal>> {
+impl VisitStmt<Result<Option<Value>>> for Interpreter {
+    fn visit_expr_stmt(&self, stmt: &Expr) -> Result<Option<Value>> {
         _ = self.evaluate(stmt)?;
         return Ok(None);
     }
 
-    fn visit_print_stmt(&self, stmt: &crate::statement::PrintStmt) -> Result<Option<Literal>> {
-        let value = self.evaluate(&stmt.0)?;
-        println!("{value}");
+    fn visit_print_stmt(&self, stmt: &crate::statement::PrintStmt) -> Result<Option<Value>> {
+        let value = self.evaluate(&stmt.1)?;
+        self.write_output(&format!("{value}\n"))
+            .map_err(|err| RuntimeError(Some(stmt.0.span), format!("Can't print, {err}.")))?;
+        Ok(None)
+    }
+
+    fn visit_var_stmt(&self, stmt: &crate::statement::Var) -> Result<Option<Value>> {
+        let value = match &stmt.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment.borrow().define(stmt.name.symbol(), value);
+        Ok(None)
+    }
+
+    fn visit_block_stmt(&self, stmt: &Block) -> Result<Option<Value>> {
+        let environment = Environment::with_enclosing(self.environment.borrow().clone());
+        self.execute_block(&stmt.0, environment)
+    }
+
+    fn visit_if_stmt(&self, stmt: &If) -> Result<Option<Value>> {
+        if self.evaluate(&stmt.condition)?.is_truthy() {
+            return self.execute(&stmt.then_branch);
+        }
+        if let Some(else_branch) = &stmt.else_branch {
+            return self.execute(else_branch);
+        }
+        Ok(None)
+    }
+
+    fn visit_while_stmt(&self, stmt: &While) -> Result<Option<Value>> {
+        while self.evaluate(&stmt.condition)?.is_truthy() {
+            if let Some(value) = self.execute(&stmt.body)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) -> Result<Option<Value>> {
+        let function = LoxFunction::new(stmt.clone(), self.environment.borrow().clone(), false);
+        self.environment
+            .borrow()
+            .define(stmt.name.symbol(), Value::Callable(Rc::new(function)));
         Ok(None)
     }
 
-    fn visit_var_stmt(&self, stmt: &crate::statement::Var) -> Result<Option<Literal>> {
-        let value = self.evaluate(&stmt.initializer)?;
-        self.environment.define(&stmt.name, value);
+    fn visit_class_stmt(&self, stmt: &Class) -> Result<Option<Value>> {
+        let superclass = match &stmt.superclass {
+            Some(variable) => match self.visit_variable(variable)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(RuntimeError(
+                        Some(variable.name.span),
+                        "Superclass must be a class.".into(),
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
+
+        let environment = self.environment.borrow().clone();
+        // Methods of a subclass close over an extra scope holding `super`
+        let mut method_environment = environment.clone();
+        if let Some(superclass) = &superclass {
+            method_environment = Environment::with_enclosing(environment.clone());
+            method_environment.define(Symbol::SUPER, Value::Class(superclass.clone()));
+        }
+        let methods = stmt
+            .methods
+            .iter()
+            .map(|method| {
+                let is_initializer = method.name.symbol() == Symbol::INIT;
+                let function =
+                    LoxFunction::new(method.clone(), method_environment.clone(), is_initializer);
+                (method.name.symbol(), Rc::new(function))
+            })
+            .collect();
+        let class = LoxClass::new(stmt.name.lexeme.to_string(), superclass, methods);
+        environment.define(stmt.name.symbol(), Value::Class(Rc::new(class)));
         Ok(None)
     }
+
+    fn visit_return_stmt(&self, stmt: &Return) -> Result<Option<Value>> {
+        let value = match &stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Ok(Some(value))
+    }
 }
 
-impl VisitExpr<Result<Literal>> for Interpreter {
-    fn visit_binary(&self, expr: &Binary) -> Result<Literal> {
+impl VisitExpr<Result<Value>> for Interpreter {
+    fn visit_binary(&self, expr: &Binary) -> Result<Value> {
         let left = self.evaluate(&expr.0)?;
         let right = self.evaluate(&expr.2)?;
+        // Errors underline the whole expression, not only the operator
+        let span = expr.0.span().to(expr.2.span());
 
         match expr.1.token_type {
             Some(TokenType::Greater) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Bool(lnum > rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Bool(lnum > rnum));
             }
             Some(TokenType::GreaterEqual) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Bool(lnum >= rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Bool(lnum >= rnum));
             }
             Some(TokenType::Less) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Bool(lnum < rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Bool(lnum < rnum));
             }
             Some(TokenType::LessEqual) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Bool(lnum <= rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Bool(lnum <= rnum));
             }
             Some(TokenType::Minus) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Num(lnum - rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Num(lnum - rnum));
             }
             Some(TokenType::Slash) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Num(lnum / rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Num(lnum / rnum));
             }
             Some(TokenType::Star) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Num(lnum * rnum));
+                let lnum = left.try_num().map_err(|_| operands_error(span))?;
+                let rnum = right.try_num().map_err(|_| operands_error(span))?;
+                return Ok(Value::Num(lnum * rnum));
             }
             Some(TokenType::Plus) => {
-                let lnum = left.try_num().map_err(|v| RuntimeError(expr.1.line, v));
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.1.line, v));
-
-                if lnum.is_ok() && rnum.is_ok() {
-                    return Ok(Literal::Num(
-                        lnum.expect("left number NaN") + rnum.expect("right number NaN"),
-                    ));
+                if let (Ok(lnum), Ok(rnum)) = (left.try_num(), right.try_num()) {
+                    return Ok(Value::Num(lnum + rnum));
+                }
+                if let (Ok(lstr), Ok(rstr)) = (left.try_str(), right.try_str()) {
+                    return Ok(Value::Str(format!("{lstr}{rstr}").into()));
                 }
-                let lstr = left
-                    .try_string()
-                    .map_err(|v| RuntimeError(expr.1.line, v))?;
-                let rstr = right
-                    .try_string()
-                    .map_err(|v| RuntimeError(expr.1.line, v))?;
-                return Ok(Literal::Str(format!("{lstr}{rstr}")));
+                return Err(RuntimeError(
+                    Some(span),
+                    "Operands must be two numbers or two strings.".into(),
+                )
+                .into());
             }
-            Some(TokenType::BangEqual) => Ok(Literal::Bool(left.ne(&right))),
-            Some(TokenType::EqualEqual) => Ok(Literal::Bool(left.eq(&right))),
+            Some(TokenType::BangEqual) => Ok(Value::Bool(left.ne(&right))),
+            Some(TokenType::EqualEqual) => Ok(Value::Bool(left.eq(&right))),
             _ => unreachable!("Binary unreachable token_type"),
         }
     }
 
-    fn visit_literal(&self, expr: &Literal) -> Result<Literal> {
-        Ok(expr.clone())
+    fn visit_literal(&self, expr: &LiteralExpr) -> Result<Value> {
+        Ok(Value::from(&expr.value))
     }
 
-    fn visit_unary(&self, expr: &Unary) -> Result<Literal> {
+    fn visit_unary(&self, expr: &Unary) -> Result<Value> {
         let right = self.evaluate(&expr.1)?;
 
         match expr.0.token_type {
             Some(TokenType::Minus) => {
-                let rnum = right.try_num().map_err(|v| RuntimeError(expr.0.line, v))?;
-                return Ok(Literal::Num(-rnum));
+                let rnum = right.try_num().map_err(|_| {
+                    RuntimeError(
+                        Some(expr.0.span.to(expr.1.span())),
+                        "Operand must be a number.".into(),
+                    )
+                })?;
+                return Ok(Value::Num(-rnum));
             }
             Some(TokenType::Bang) => {
-                return Ok(Literal::Bool(!right.is_truthy()));
+                return Ok(Value::Bool(!right.is_truthy()));
             }
             _ => Ok(right),
         }
     }
 
-    fn visit_grouping(&self, expr: &Grouping) -> Result<Literal> {
+    fn visit_grouping(&self, expr: &Grouping) -> Result<Value> {
         self.evaluate(&expr.0)
     }
 
-    fn visit_variable(&self, expr: &crate::ast::Variable) -> Result<Literal> {
-        self.environment.get(&expr.name)
+    fn visit_variable(&self, expr: &Variable) -> Result<Value> {
+        match expr.depth.get() {
+            Some(distance) => self.environment.borrow().get_at(distance, &expr.name),
+            None => self.globals.get(&expr.name),
+        }
     }
 
-    fn visit_assignment(&self, expr: &crate::ast::Assignment) -> Result<Literal> {
+    fn visit_assignment(&self, expr: &crate::ast::Assignment) -> Result<Value> {
         let value = self.evaluate(&expr.value)?;
-        _ = self.environment.assign(&expr.name, value.clone())?;
+        match expr.depth.get() {
+            Some(distance) => {
+                self.environment
+                    .borrow()
+                    .assign_at(distance, &expr.name, value.clone())?;
+            }
+            None => self.globals.assign(&expr.name, value.clone())?,
+        }
         return Ok(value);
     }
+
+    fn visit_logical(&self, expr: &Logical) -> Result<Value> {
+        let left = self.evaluate(&expr.0)?;
+        // Short-circuit returning the operand that decided the result
+        match expr.1.token_type {
+            Some(TokenType::Or) if left.is_truthy() => Ok(left),
+            Some(TokenType::And) if !left.is_truthy() => Ok(left),
+            _ => self.evaluate(&expr.2),
+        }
+    }
+
+    fn visit_call(&self, expr: &Call) -> Result<Value> {
+        let callee = self.evaluate(&expr.callee)?;
+        let arguments = expr
+            .arguments
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>>>()?;
+
+        let span = expr.callee.span().to(expr.paren.span);
+        let function: &dyn LoxCallable = match &callee {
+            Value::Callable(function) => function.as_ref(),
+            Value::Class(class) => class,
+            _ => {
+                return Err(
+                    RuntimeError(Some(span), "Can only call functions and classes.".into()).into(),
+                )
+            }
+        };
+        if arguments.len() != function.arity() {
+            return Err(RuntimeError(
+                Some(span),
+                format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    arguments.len()
+                ),
+            )
+            .into());
+        }
+        if self.depth.get() >= self.max_depth.get() {
+            return Err(RuntimeError(Some(span), "Stack overflow.".into()).into());
+        }
+        self.depth.set(self.depth.get() + 1);
+        let result = function.call(self, arguments);
+        self.depth.set(self.depth.get() - 1);
+        // Natives don't know where they were called from, errors that
+        // aren't already runtime errors get the location of the call
+        result.map_err(|err| {
+            if err.is::<RuntimeError>() {
+                err
+            } else {
+                RuntimeError(Some(span), err.to_string()).into()
+            }
+        })
+    }
+
+    fn visit_get(&self, expr: &Get) -> Result<Value> {
+        match self.evaluate(&expr.object)? {
+            Value::Instance(instance) => instance.get(&expr.name),
+            _ => Err(RuntimeError(
+                Some(expr.object.span()),
+                "Only instances have properties.".into(),
+            )
+            .into()),
+        }
+    }
+
+    fn visit_set(&self, expr: &Set) -> Result<Value> {
+        let Value::Instance(instance) = self.evaluate(&expr.object)? else {
+            return Err(RuntimeError(
+                Some(expr.object.span()),
+                "Only instances have fields.".into(),
+            )
+            .into());
+        };
+        let value = self.evaluate(&expr.value)?;
+        instance.set(expr.name.symbol(), value.clone());
+        Ok(value)
+    }
+
+    fn visit_this(&self, expr: &This) -> Result<Value> {
+        match expr.depth.get() {
+            Some(distance) => self.environment.borrow().get_at(distance, &expr.keyword),
+            None => self.globals.get(&expr.keyword),
+        }
+    }
+
+    fn visit_super(&self, expr: &Super) -> Result<Value> {
+        let distance = expr
+            .depth
+            .get()
+            .expect("The resolver always binds 'super' to a local scope");
+        let environment = self.environment.borrow();
+        let superclass = environment.ancestor(distance).get_local(Symbol::SUPER);
+        // `this` lives in the scope right inside the one holding `super`
+        let object = environment.ancestor(distance - 1).get_local(Symbol::THIS);
+        let (Some(Value::Class(superclass)), Some(Value::Instance(object))) = (superclass, object)
+        else {
+            unreachable!("'super' and 'this' are bound when the method is created")
+        };
+        match superclass.find_method(expr.method.symbol()) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(object)))),
+            None => Err(RuntimeError(
+                Some(expr.method.span),
+                format!("Undefined property '{}'.", expr.method.lexeme),
+            )
+            .into()),
+        }
+    }
 }
@@ -0,0 +1,332 @@
+#![warn(clippy::pedantic)]
+#![allow(
+    clippy::needless_return,
+    clippy::vec_box,
+    clippy::upper_case_acronyms,
+    clippy::struct_field_names
+)]
+//! Tree-walking interpreter for the Lox language from
+//! [Crafting Interpreters](https://craftinginterpreters.com).
+//!
+//! [`Lox`] runs scripts and exchanges values with the host:
+//!
+//! ```
+//! use lox_rs::{Value, Lox};
+//!
+//! let lox = Lox::new();
+//! lox.eval_str("fun add(a, b) { return a + b; }").unwrap();
+//! let sum = lox.call_function("add", vec![Value::Num(1.0), Value::Num(2.0)]);
+//! assert_eq!(sum.unwrap(), Value::Num(3.0));
+//! ```
+mod ast;
+mod callable;
+mod class;
+mod convert;
+mod diagnostics;
+mod env;
+mod error;
+mod intern;
+mod interpreter;
+mod native;
+mod parser;
+mod resolver;
+mod scanner;
+mod sink;
+mod statement;
+mod value;
+
+use std::{cell::RefCell, fs, io, path::Path};
+
+pub use convert::{ConversionError, FromLox, IntoLox};
+pub use error::Error;
+pub use interpreter::RuntimeError;
+pub use native::{IntoNative, NativeReturn};
+pub use parser::{ParserError, SyntaxErrors};
+pub use resolver::ResolverError;
+pub use scanner::{LexError, LexErrorKind, Scanner, Span, Token, TokenType};
+pub use sink::Sink;
+pub use value::{LoxString, Value};
+
+use callable::LoxCallable;
+use interpreter::Interpreter;
+
+/// An interpreter session, globals defined by one call are seen by the next.
+pub struct Lox {
+    interpreter: Interpreter,
+    diagnostics: RefCell<Sink>,
+    print_tokens: bool,
+    print_ast: bool,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    /// Creates a session with the standard library loaded.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+            diagnostics: RefCell::new(Sink::Stderr),
+            print_tokens: false,
+            print_ast: false,
+        }
+    }
+
+    /// Sends what scripts `print`, and the debugging output, to `sink`
+    /// instead of stdout.
+    ///
+    /// ```
+    /// use std::{cell::RefCell, rc::Rc};
+    /// use lox_rs::{Lox, Sink};
+    ///
+    /// let printed = Rc::new(RefCell::new(String::new()));
+    /// let buffer = printed.clone();
+    /// let mut lox = Lox::new();
+    /// lox.set_output(Sink::callback(move |text| buffer.borrow_mut().push_str(text)));
+    /// lox.eval_str("print 1 + 2;").unwrap();
+    /// assert_eq!(*printed.borrow(), "3\n");
+    /// ```
+    pub fn set_output(&mut self, sink: Sink) {
+        self.interpreter.set_output(sink);
+    }
+
+    /// Makes calls nested deeper than `depth` fail with a `Stack overflow.`
+    /// runtime error instead of overflowing the host's stack, 1000 by
+    /// default.
+    ///
+    /// A call takes up to about 16 KiB of stack in debug builds and 4 KiB
+    /// in release builds, the default needs a 16 MiB stack in the worst
+    /// case. Lower it, or run `Lox` on a thread with a bigger stack as the
+    /// CLI does.
+    ///
+    /// ```
+    /// use lox_rs::Lox;
+    ///
+    /// let mut lox = Lox::new();
+    /// lox.set_max_call_depth(10);
+    /// let err = lox.eval_str("fun f(n) { if (n > 0) f(n - 1); } f(20);").unwrap_err();
+    /// assert!(err.to_string().contains("Stack overflow."));
+    /// ```
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.interpreter.set_max_call_depth(depth);
+    }
+
+    /// Sends the diagnostics written by [`Lox::report`] to `sink` instead
+    /// of stderr.
+    pub fn set_diagnostics(&mut self, sink: Sink) {
+        self.diagnostics.replace(sink);
+    }
+
+    /// Prints the tokens of every source evaluated, for debugging.
+    pub fn set_print_tokens(&mut self, enabled: bool) {
+        self.print_tokens = enabled;
+    }
+
+    /// Prints the syntax tree of every source evaluated, for debugging.
+    pub fn set_print_ast(&mut self, enabled: bool) {
+        self.print_ast = enabled;
+    }
+
+    /// Runs `source`, returning the value of its last statement when it's
+    /// an expression.
+    ///
+    /// # Errors
+    ///
+    /// Fails with every syntax or resolver error found, or with the first
+    /// runtime error.
+    pub fn eval_str(&self, source: &str) -> Result<Option<Value>, Error> {
+        if self.print_tokens {
+            // Scans a second time, the parser pulls its own tokens
+            let tokens: Vec<_> = Scanner::new(source).collect();
+            self.interpreter.write_output(&format!("{tokens:#?}\n"))?;
+        }
+        let statements =
+            parser::Parser::new(Scanner::new(source), self.interpreter.interner()).parse()?;
+        resolver::Resolver::new().resolve(&statements)?;
+
+        if self.print_ast {
+            self.interpreter
+                .write_output(&format!("{statements:#?}\n"))?;
+        }
+
+        self.interpreter
+            .interpret(statements)
+            .map_err(Error::from_runtime)
+    }
+
+    /// Evaluates the single expression `source` without changing the
+    /// session, assignments and calls are rejected since they could.
+    ///
+    /// ```
+    /// use lox_rs::{Lox, Value};
+    ///
+    /// let lox = Lox::new();
+    /// lox.eval_str("var count = 1;").unwrap();
+    /// assert_eq!(lox.eval_expr("count + 1").unwrap(), Value::Num(2.0));
+    /// assert!(lox.eval_expr("count = 2").is_err());
+    /// assert_eq!(lox.get_global("count"), Some(Value::Num(1.0)));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails when `source` isn't a single expression, has side effects or
+    /// like [`Lox::eval_str`].
+    pub fn eval_expr(&self, source: &str) -> Result<Value, Error> {
+        let expr = parser::Parser::new(Scanner::new(source), self.interpreter.interner())
+            .parse_expression()?;
+        if let Some(span) = expr.side_effect() {
+            return Err(Error::Resolve(vec![ResolverError {
+                span,
+                message: "Can't evaluate an expression with side effects.".into(),
+            }]));
+        }
+        let statements = vec![Box::new(statement::Stmt::Expr(expr))];
+        resolver::Resolver::new().resolve(&statements)?;
+        let value = self
+            .interpreter
+            .interpret(statements)
+            .map_err(Error::from_runtime)?;
+        Ok(value.unwrap_or(Value::Nil))
+    }
+
+    /// Reads the script at `path` and runs it.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file can't be read or like [`Lox::eval_str`].
+    pub fn run_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let source = fs::read_to_string(path)?;
+        self.eval_str(&source)?;
+        Ok(())
+    }
+
+    /// Writes `err` as diagnostics quoting `source`, the script it came
+    /// from is called `filename`.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the diagnostics sink can't be written to.
+    pub fn report(&self, err: &Error, filename: &str, source: &str) -> io::Result<()> {
+        diagnostics::report(err, filename, source, &mut self.diagnostics.borrow_mut())
+    }
+
+    /// Defines a global variable, replacing any previous value.
+    pub fn set_global(&self, name: &str, value: impl IntoLox) {
+        let name = self.interpreter.interner().borrow_mut().intern(name);
+        self.interpreter.globals().define(name, value.into_lox());
+    }
+
+    /// Value of the global variable `name`, if it's defined.
+    #[must_use]
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        // A name that was never interned can't be defined
+        let name = self.interpreter.interner().borrow().lookup(name)?;
+        self.interpreter.globals().get_local(name)
+    }
+
+    /// Global bindings sorted by name, functions and classes included.
+    #[must_use]
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        self.interpreter
+            .globals()
+            .bindings(&self.interpreter.interner().borrow())
+    }
+
+    /// Calls the global function or class `name`.
+    ///
+    /// ```
+    /// use lox_rs::{Lox, Value};
+    ///
+    /// let lox = Lox::new();
+    /// lox.eval_str("fun add(a, b) { return a + b; }").unwrap();
+    /// let err = lox.call_function("add", vec![Value::Num(1.0)]).unwrap_err();
+    /// assert_eq!(err.to_string(), "Expected 2 arguments but got 1.");
+    ///
+    /// let err = lox.eval_str("add(1, nil);").unwrap_err();
+    /// assert_eq!(err.to_string(), "1:24: Operands must be two numbers or two strings.");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails when `name` isn't callable, the number of arguments doesn't
+    /// match or the call itself fails.
+    pub fn call_function(&self, name: &str, arguments: Vec<Value>) -> Result<Value, Error> {
+        let callee = self.get_global(name);
+        let function: &dyn LoxCallable = match &callee {
+            Some(Value::Callable(function)) => function.as_ref(),
+            Some(Value::Class(class)) => class,
+            Some(_) => return Err(host_error(format!("'{name}' is not callable."))),
+            None => return Err(host_error(format!("Undefined variable '{name}'."))),
+        };
+        if arguments.len() != function.arity() {
+            return Err(host_error(format!(
+                "Expected {} arguments but got {}.",
+                function.arity(),
+                arguments.len()
+            )));
+        }
+        function
+            .call(&self.interpreter, arguments)
+            .map_err(Error::from_runtime)
+    }
+
+    /// Defines the globals scripts use to handle the lists and maps
+    /// converted from `Vec` and `HashMap`: `list()` and `map()` create
+    /// them, `length(collection)`, `get(collection, key)` and
+    /// `set(collection, key, value)` use them. Setting the index right
+    /// after the last element appends to a list.
+    ///
+    /// ```
+    /// use lox_rs::{FromLox, Lox};
+    ///
+    /// let lox = Lox::new();
+    /// lox.register_collections();
+    /// let list = lox.eval_str("var l = list(); set(l, 0, 4); set(l, 1, 2); l;");
+    /// assert_eq!(Vec::<u8>::from_lox(list.unwrap().unwrap()), Ok(vec![4, 2]));
+    ///
+    /// lox.set_global("ages", std::collections::HashMap::from([("ada".to_string(), 36)]));
+    /// let age = lox.eval_str(r#"get(ages, "ada") + length(ages);"#).unwrap().unwrap();
+    /// assert_eq!(f64::from_lox(age), Ok(37.0));
+    ///
+    /// let err = lox.eval_str("get(list(), 0);").unwrap_err();
+    /// assert!(err.to_string().ends_with("List index out of range."));
+    /// ```
+    pub fn register_collections(&self) {
+        for native in native::collections() {
+            self.interpreter.define_native(native);
+        }
+    }
+
+    /// Makes a Rust closure callable from Lox as the global `name`.
+    ///
+    /// Its parameters and return value are converted with [`FromLox`] and
+    /// [`IntoLox`], arguments of the wrong type and returned errors are
+    /// reported as runtime errors at the call site.
+    ///
+    /// ```
+    /// use lox_rs::{Value, Lox};
+    ///
+    /// let lox = Lox::new();
+    /// lox.register_native("repeat", |text: String, times: usize| text.repeat(times));
+    /// let value = lox.eval_str(r#"repeat("ab", 3);"#).unwrap();
+    /// assert_eq!(value, Some(Value::Str("ababab".into())));
+    ///
+    /// let err = lox.eval_str(r#"repeat("ab", 1.5);"#).unwrap_err();
+    /// assert!(err.to_string().contains("Invalid argument 2, expected usize but got 1.5."));
+    /// ```
+    pub fn register_native<Args>(&self, name: &str, function: impl IntoNative<Args> + 'static) {
+        self.interpreter
+            .register_native(name, function.arity(), move |_, arguments| {
+                function.call(arguments).map_err(anyhow::Error::msg)
+            });
+    }
+}
+
+// Errors raised by the host through the API don't point at any source
+fn host_error(message: String) -> Error {
+    Error::Runtime(RuntimeError(None, message))
+}
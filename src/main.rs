@@ -1,36 +1,19 @@
-#![warn(clippy::pedantic)]
-mod ast;
-mod env;
-mod interpreter;
-mod parser;
-mod scanner;
-mod statement;
-
-use anyhow::Result;
+mod repl;
 
 use clap::Parser;
 
-use std::io::Write;
 use std::path::PathBuf;
-use std::{error, fmt, fs, io};
-
-use interpreter::{Interpreter, RuntimeError};
-
-// struct Jlox;
+use std::{error, fs, thread};
 
-#[derive(Debug)]
-pub struct JLoxError(usize, String);
-
-impl fmt::Display for JLoxError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error:\nline {}: {}", self.0, self.1)
-    }
-}
-impl error::Error for JLoxError {}
+use lox_rs::{Error, Lox};
 
 type JloxResult = Result<(), Box<dyn error::Error>>;
 
-#[derive(Parser, Debug)]
+// Scripts run on a thread of their own, with room for many times the
+// default maximum call depth even in debug builds
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Parser, Debug, Clone)]
 #[clap(name = "rslox", version, about)]
 struct Args {
     /// Output the tokens tree generated
@@ -46,73 +29,50 @@ struct Args {
     path: Option<PathBuf>,
 }
 
-struct Program {
-    interpreter: Interpreter,
-}
-impl Program {
-    fn new() -> Self {
-        Self {
-            interpreter: Interpreter::new(),
-        }
+impl Args {
+    fn session(&self) -> Lox {
+        let mut lox = Lox::new();
+        lox.set_print_tokens(self.print_tokens);
+        lox.set_print_ast(self.print_ast);
+        lox
     }
 }
 
 fn main() -> JloxResult {
     let args = Args::parse();
+    let interpreter = thread::Builder::new()
+        .name("interpreter".into())
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&args).map_err(|err| err.to_string()))?;
+    interpreter
+        .join()
+        .expect("The interpreter thread panicked")?;
+    Ok(())
+}
+
+fn run(args: &Args) -> JloxResult {
     if let Some(path) = &args.path {
-        run_file(path, &args)?
+        run_file(path, args);
     } else {
-        run_prompt(&args)?
+        repl::run_prompt(args)?;
     }
 
     Ok(())
 }
 
-fn run_prompt(args: &Args) -> JloxResult {
-    let program = Program::new();
-    loop {
-        print!("> ");
-        io::stdout().flush().expect("Could not flush");
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        if let Some(err) = run(input, args, &program).err() {
-            eprintln!("{:?}", err);
-        };
-    }
-}
-
-fn run_file(filepath: &PathBuf, args: &Args) -> JloxResult {
-    let source = fs::read_to_string(filepath)?;
-    let program = Program::new();
-    if let Some(err) = run(source, args, &program).err() {
-        eprintln!("{:?}", err);
-        for cause in err.chain() {
-            if let Some(_) = cause.downcast_ref::<parser::ParserError>() {
-                std::process::exit(65)
-            }
-            if let Some(_) = cause.downcast_ref::<RuntimeError>() {
-                std::process::exit(70)
-            }
+// The source is read here instead of `Lox::run_file`, diagnostics quote it
+fn run_file(filepath: &PathBuf, args: &Args) {
+    let filename = filepath.display().to_string();
+    let source = match fs::read_to_string(filepath) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("error: can't read '{filename}': {err}");
+            std::process::exit(Error::from(err).exit_code())
         }
-        std::process::exit(65)
     };
-    Ok(())
-}
-
-fn run(source: String, args: &Args, program: &Program) -> Result<()> {
-    let mut scanner = scanner::Scanner::new(source);
-    let _ = scanner.scan_tokens();
-    if args.print_tokens {
-        println!("{:#?}", scanner.tokens);
+    let lox = args.session();
+    if let Err(err) = lox.eval_str(&source) {
+        let _ = lox.report(&err, &filename, &source);
+        std::process::exit(err.exit_code())
     }
-    let mut parser = parser::Parser::new(scanner.tokens);
-    let exprs = parser.parse()?;
-
-    if args.print_ast {
-        println!("{:#?}", exprs);
-    }
-
-    let _ = program.interpreter.interpret(exprs)?;
-
-    Ok(())
 }
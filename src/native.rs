@@ -0,0 +1,218 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Error, Result};
+
+use crate::{
+    callable::LoxCallable,
+    convert::{FromLox, IntoLox},
+    interpreter::Interpreter,
+    value::Value,
+};
+
+pub type NativeFn = dyn Fn(&Interpreter, Vec<Value>) -> Result<Value>;
+
+// Function implemented in Rust and exposed to Lox as a global.
+// Errors returned by `function` are reported as runtime errors at the call site.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        function: impl Fn(&Interpreter, Vec<Value>) -> Result<Value> + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+/// Values a host function can return, errors are reported as runtime
+/// errors at the call site.
+pub trait NativeReturn {
+    /// # Errors
+    ///
+    /// Fails when the host function failed.
+    fn into_result(self) -> Result<Value, String>;
+}
+
+impl<T: IntoLox> NativeReturn for T {
+    fn into_result(self) -> Result<Value, String> {
+        Ok(self.into_lox())
+    }
+}
+
+impl<T: IntoLox, E: fmt::Display> NativeReturn for std::result::Result<T, E> {
+    fn into_result(self) -> Result<Value, String> {
+        self.map(IntoLox::into_lox).map_err(|err| err.to_string())
+    }
+}
+
+/// Rust closures that can be called from Lox, `Args` are the types of
+/// their parameters which give the arity of the function.
+pub trait IntoNative<Args> {
+    fn arity(&self) -> usize;
+
+    /// # Errors
+    ///
+    /// Fails when an argument has the wrong type or the function failed.
+    fn call(&self, arguments: Vec<Value>) -> Result<Value, String>;
+}
+
+macro_rules! into_native {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> IntoNative<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R,
+            R: NativeReturn,
+            $($arg: FromLox,)*
+        {
+            fn arity(&self) -> usize {
+                <[&str]>::len(&[$(stringify!($arg)),*])
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, arguments: Vec<Value>) -> Result<Value, String> {
+                let mut arguments = arguments.into_iter().enumerate();
+                $(
+                    let (index, argument) = arguments.next().expect("Arity is checked by the caller");
+                    let $arg = $arg::from_lox(argument)
+                        .map_err(|err| format!("Invalid argument {}, {err}.", index + 1))?;
+                )*
+                self($($arg),*).into_result()
+            }
+        }
+    };
+}
+
+into_native!();
+into_native!(A);
+into_native!(A, B);
+into_native!(A, B, C);
+into_native!(A, B, C, D);
+into_native!(A, B, C, D, E);
+into_native!(A, B, C, D, E, G);
+
+// Natives defined in the globals of every interpreter
+pub fn standard_library() -> Vec<NativeFunction> {
+    vec![NativeFunction::new("clock", 0, |_, _| clock())]
+}
+
+// Natives working on the lists and maps of the host, only defined when
+// the host asks for them
+pub fn collections() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("list", 0, |_, _| Ok(Vec::<Value>::new().into_lox())),
+        NativeFunction::new("map", 0, |_, _| {
+            Ok(HashMap::<String, Value>::new().into_lox())
+        }),
+        NativeFunction::new("length", 1, |_, arguments| match &arguments[0] {
+            Value::List(values) => Ok(values.borrow().len().into_lox()),
+            Value::Map(entries) => Ok(entries.borrow().len().into_lox()),
+            _ => Err(Error::msg(NOT_A_COLLECTION)),
+        }),
+        NativeFunction::new("get", 2, |_, arguments| get(&arguments[0], &arguments[1])),
+        NativeFunction::new("set", 3, |_, mut arguments| {
+            let value = arguments.pop().expect("Arity is checked by the caller");
+            set(&arguments[0], &arguments[1], value.clone())?;
+            Ok(value)
+        }),
+    ]
+}
+
+const NOT_A_COLLECTION: &str = "Only lists and maps have elements.";
+
+// A key missing from a map is `nil`
+fn get(collection: &Value, key: &Value) -> Result<Value> {
+    match collection {
+        Value::List(values) => values
+            .borrow()
+            .get(list_index(key)?)
+            .cloned()
+            .ok_or_else(|| Error::msg("List index out of range.")),
+        Value::Map(entries) => {
+            let entries = entries.borrow();
+            Ok(entries.get(map_key(key)?).cloned().unwrap_or(Value::Nil))
+        }
+        _ => Err(Error::msg(NOT_A_COLLECTION)),
+    }
+}
+
+// Setting the index right after the last element appends to a list
+fn set(collection: &Value, key: &Value, value: Value) -> Result<()> {
+    match collection {
+        Value::List(values) => {
+            let index = list_index(key)?;
+            let mut values = values.borrow_mut();
+            match index.cmp(&values.len()) {
+                Ordering::Less => values[index] = value,
+                Ordering::Equal => values.push(value),
+                Ordering::Greater => return Err(Error::msg("List index out of range.")),
+            }
+        }
+        Value::Map(entries) => {
+            entries
+                .borrow_mut()
+                .insert(map_key(key)?.to_string(), value);
+        }
+        _ => return Err(Error::msg(NOT_A_COLLECTION)),
+    }
+    Ok(())
+}
+
+fn list_index(key: &Value) -> Result<usize> {
+    usize::from_lox(key.clone()).map_err(|_| Error::msg("List index must be a whole number."))
+}
+
+fn map_key(key: &Value) -> Result<&str> {
+    match key {
+        Value::Str(key) => Ok(key.as_str()),
+        _ => Err(Error::msg("Map key must be a string.")),
+    }
+}
+
+// Seconds since the unix epoch, used to time the benchmarks
+fn clock() -> Result<Value> {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    Ok(Value::Num(elapsed.as_secs_f64()))
+}
@@ -1,30 +1,64 @@
 use crate::{
-    ast::{Assignment, Binary, Expr, Grouping, Literal, Unary, Variable},
-    scanner::{Token, TokenType},
-    statement::{PrintStmt, Stmt, Var},
+    ast::{
+        Assignment, Binary, Call, Expr, Get, Grouping, Literal, LiteralExpr, Logical, Set, Super,
+        This, Unary, Variable,
+    },
+    intern::Interner,
+    scanner::{LexError, Scanner, Span, Token, TokenType},
+    statement::{Block, Class, Function, If, PrintStmt, Return, Stmt, Var, While},
 };
 
-use std::{cell::RefCell, error, fmt, rc::Rc};
+use std::{
+    cell::{Ref, RefCell},
+    error, fmt,
+    rc::Rc,
+};
+
+const MAX_ARGUMENTS: usize = 255;
 
 #[derive(Debug)]
 pub struct ParserError {
-    pub line: usize,
+    pub span: Span,
     pub message: String,
 }
 
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ParserError:\n\n\t[Line {}] {}", self.line, self.message)
+        write!(f, "{}: {}", self.span, self.message)
     }
 }
 impl error::Error for ParserError {}
 
+// Every lexical error and every error the parser recovered from,
+// each in source order
+#[derive(Debug)]
+pub struct SyntaxErrors {
+    pub lex_errors: Vec<LexError>,
+    pub parse_errors: Vec<ParserError>,
+}
+
+impl fmt::Display for SyntaxErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lex_errors = self.lex_errors.iter().map(ToString::to_string);
+        let parse_errors = self.parse_errors.iter().map(ToString::to_string);
+        let errors: Vec<String> = lex_errors.chain(parse_errors).collect();
+        write!(f, "{}", errors.join("\n"))
+    }
+}
+impl error::Error for SyntaxErrors {}
+
 type ParserResult<T> = Result<T, ParserError>;
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    // current: usize,
-    current: Rc<RefCell<usize>>,
+// Tokens are pulled from the scanner on demand, `current` is the only
+// lookahead the grammar needs
+pub struct Parser<'a> {
+    tokens: RefCell<Scanner<'a>>,
+    current: RefCell<Token<'a>>,
+    previous: RefCell<Token<'a>>,
+    errors: RefCell<Vec<ParserError>>,
+    lex_errors: RefCell<Vec<LexError>>,
+    // Names and strings are interned as they are pulled from the scanner
+    interner: &'a RefCell<Interner>,
 }
 
 // Build the AST syntax tree
@@ -32,74 +66,326 @@ pub struct Parser {
 // Expressions are split into different rules to have
 // precedence and associativity, in order to avoid
 // ambiguous results.
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self {
-            tokens,
-            current: Rc::new(RefCell::new(0)),
-        }
+impl<'a> Parser<'a> {
+    pub fn new(tokens: Scanner<'a>, interner: &'a RefCell<Interner>) -> Self {
+        let parser = Self {
+            tokens: RefCell::new(tokens),
+            current: RefCell::default(),
+            previous: RefCell::default(),
+            errors: RefCell::new(vec![]),
+            lex_errors: RefCell::new(vec![]),
+            interner,
+        };
+        parser.current.replace(parser.next_token());
+        parser
     }
 
-    pub fn parse(&mut self) -> ParserResult<Vec<Box<Stmt>>> {
+    // Parsing goes on after an error to report as many as possible,
+    // the statements are only returned when there were none
+    pub fn parse(&mut self) -> Result<Vec<Box<Stmt>>, SyntaxErrors> {
         let mut stmts = vec![];
         while !self.is_at_end() {
-            let decl = self.declaration();
-            match decl {
-                Ok(stmt) => stmts.push(stmt),
-                Err(_) => {
-                    self.synchronize();
-                }
+            if let Some(stmt) = self.declaration() {
+                stmts.push(stmt);
             }
-            // stmts.push(self.statement()?);
         }
+        self.take_errors()?;
         return Ok(stmts);
     }
 
-    fn declaration(&mut self) -> ParserResult<Box<Stmt>> {
+    // A lone expression without the `;`, for the REPL's `:type`
+    pub fn parse_expression(&mut self) -> Result<Box<Expr>, SyntaxErrors> {
+        let expr = self.expression().and_then(|expr| {
+            if !self.is_at_end() {
+                return Err(ParserError {
+                    span: self.peek().span,
+                    message: "Expect end of expression.".into(),
+                });
+            }
+            Ok(expr)
+        });
+        let expr = expr.map_err(|err| self.report(err));
+        self.take_errors()?;
+        return Ok(expr.expect("The error was reported"));
+    }
+
+    fn take_errors(&self) -> Result<(), SyntaxErrors> {
+        let lex_errors = self.lex_errors.take();
+        let parse_errors = self.errors.take();
+        if !lex_errors.is_empty() || !parse_errors.is_empty() {
+            return Err(SyntaxErrors {
+                lex_errors,
+                parse_errors,
+            });
+        }
+        return Ok(());
+    }
+
+    // Records an error that doesn't leave the parser in a confused state,
+    // so there is no need to synchronize
+    fn report(&self, error: ParserError) {
+        self.errors.borrow_mut().push(error);
+    }
+
+    // Errors are recovered from right where they happen, so a block goes on
+    // with the statements following the broken one
+    fn declaration(&mut self) -> Option<Box<Stmt>> {
+        match self.declaration_or_error() {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.report(err);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    fn declaration_or_error(&mut self) -> ParserResult<Box<Stmt>> {
+        if self.fits(vec![TokenType::Class]) {
+            return self.class_declaration();
+        }
+        if self.fits(vec![TokenType::Fun]) {
+            return Ok(Box::new(Stmt::Function(self.function("function")?)));
+        }
         if self.fits(vec![TokenType::Var]) {
             return self.var_declaration();
         }
         return self.statement();
     }
 
+    fn class_declaration(&mut self) -> ParserResult<Box<Stmt>> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.".into())?
+            .into_owned();
+        let mut superclass = None;
+        if self.fits(vec![TokenType::Less]) {
+            let superclass_name = self
+                .consume(TokenType::Identifier, "Expect superclass name.".into())?
+                .into_owned();
+            superclass = Some(Variable::new(superclass_name));
+        }
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.".into())?;
+        let mut methods = vec![];
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.".into())?;
+        return Ok(Box::new(Stmt::Class(Class {
+            name,
+            superclass,
+            methods,
+        })));
+    }
+
+    // `kind` is only used to improve the error messages
+    fn function(&mut self, kind: &str) -> ParserResult<Rc<Function>> {
+        let name = self
+            .consume(TokenType::Identifier, format!("Expect {kind} name."))?
+            .into_owned();
+        self.consume(
+            TokenType::LeftParen,
+            format!("Expect '(' after {kind} name."),
+        )?;
+        let mut params = vec![];
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    self.report(ParserError {
+                        span: self.peek().span,
+                        message: format!("Can't have more than {MAX_ARGUMENTS} parameters."),
+                    });
+                }
+                params.push(
+                    self.consume(TokenType::Identifier, "Expect parameter name.".into())?
+                        .into_owned(),
+                );
+                if !self.fits(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.".into())?;
+        self.consume(
+            TokenType::LeftBrace,
+            format!("Expect '{{' before {kind} body."),
+        )?;
+        let body = self.block()?;
+        return Ok(Rc::new(Function { name, params, body }));
+    }
+
     fn var_declaration(&mut self) -> ParserResult<Box<Stmt>> {
-        let token = self.consume(TokenType::Identifier, "Expect variable name.".into())?;
-        let initializer: Box<Expr>;
+        let token = self
+            .consume(TokenType::Identifier, "Expect variable name.".into())?
+            .into_owned();
+        let mut initializer = None;
         if self.fits(vec![TokenType::Equal]) {
-            initializer = self.expression()?;
-        } else {
-            return Err(ParserError {
-                line: token.line,
-                message: "No token matched during var_declaration".into(),
-            });
+            initializer = Some(self.expression()?);
         }
-        _ = self.consume(
+        self.consume(
             TokenType::Semicolon,
-            "Expect ';' after variable declaration".into(),
-        );
+            "Expect ';' after variable declaration.".into(),
+        )?;
         return Ok(Box::new(Stmt::Var(Var {
-            name: token.clone(),
+            name: token,
             initializer,
         })));
     }
 
     fn statement(&mut self) -> ParserResult<Box<Stmt>> {
+        if self.fits(vec![TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.fits(vec![TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.fits(vec![TokenType::While]) {
+            return self.while_statement();
+        }
         if self.fits(vec![TokenType::Print]) {
             return self.print_statement();
         }
+        if self.fits(vec![TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.fits(vec![TokenType::LeftBrace]) {
+            let start = self.previous().span;
+            let stmts = self.block()?;
+            let span = start.to(self.previous().span);
+            return Ok(Box::new(Stmt::Block(Block(stmts, span))));
+        }
         return self.expression_statement();
     }
 
+    // There is no `for` node, the loop is desugared into
+    // `{ initializer; while (condition) { body; increment; } }`
+    fn for_statement(&mut self) -> ParserResult<Box<Stmt>> {
+        let keyword = self.previous().into_owned();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.".into())?;
+        let initializer = if self.fits(vec![TokenType::Semicolon]) {
+            None
+        } else if self.fits(vec![TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let mut condition = None;
+        if !self.check(TokenType::Semicolon) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after loop condition.".into(),
+        )?;
+
+        let mut increment = None;
+        if !self.check(TokenType::RightParen) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after for clauses.".into(),
+        )?;
+
+        let mut body = self.statement()?;
+        // The desugared blocks cover the whole `for` statement
+        let span = keyword.span.to(self.previous().span);
+        if let Some(increment) = increment {
+            body = Box::new(Stmt::Block(Block(
+                vec![body, Box::new(Stmt::Expr(increment))],
+                span,
+            )));
+        }
+        // A missing condition is an implicit `true` located at the `for`
+        let condition = condition.unwrap_or_else(|| {
+            Box::new(Expr::Literal(LiteralExpr {
+                value: Literal::Bool(true),
+                span: keyword.span,
+            }))
+        });
+        body = Box::new(Stmt::While(While {
+            keyword: keyword.clone(),
+            condition,
+            body,
+        }));
+        if let Some(initializer) = initializer {
+            body = Box::new(Stmt::Block(Block(vec![initializer, body], span)));
+        }
+        return Ok(body);
+    }
+
+    fn if_statement(&mut self) -> ParserResult<Box<Stmt>> {
+        let keyword = self.previous().into_owned();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.".into())?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after if condition.".into(),
+        )?;
+
+        let then_branch = self.statement()?;
+        // The `else` is bound to the nearest `if`
+        let mut else_branch = None;
+        if self.fits(vec![TokenType::Else]) {
+            else_branch = Some(self.statement()?);
+        }
+        return Ok(Box::new(Stmt::If(If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        })));
+    }
+
+    fn while_statement(&mut self) -> ParserResult<Box<Stmt>> {
+        let keyword = self.previous().into_owned();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.".into())?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.".into())?;
+        let body = self.statement()?;
+        return Ok(Box::new(Stmt::While(While {
+            keyword,
+            condition,
+            body,
+        })));
+    }
+
+    // Assumes the `{` has already been consumed
+    fn block(&mut self) -> ParserResult<Vec<Box<Stmt>>> {
+        let mut stmts = vec![];
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(stmt) = self.declaration() {
+                stmts.push(stmt);
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after block.".into())?;
+        return Ok(stmts);
+    }
+
     fn expression_statement(&mut self) -> Result<Box<Stmt>, ParserError> {
         let expr = self.expression()?;
-        _ = self.consume(TokenType::Semicolon, "Expect ';' after value.".into());
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.".into())?;
         return Ok(Box::new(Stmt::Expr(expr)));
     }
 
     fn print_statement(&mut self) -> ParserResult<Box<Stmt>> {
+        let keyword = self.previous().into_owned();
         let expr = self.expression()?;
-        _ = self.consume(TokenType::Semicolon, "Expect ';' after value.".into());
-        return Ok(Box::new(Stmt::PrintStmt(PrintStmt(expr))));
+        self.consume(TokenType::Semicolon, "Expect ';' after value.".into())?;
+        return Ok(Box::new(Stmt::PrintStmt(PrintStmt(keyword, expr))));
+    }
+
+    fn return_statement(&mut self) -> ParserResult<Box<Stmt>> {
+        let keyword = self.previous().into_owned();
+        let mut value = None;
+        if !self.check(TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after return value.".into(),
+        )?;
+        return Ok(Box::new(Stmt::Return(Return { keyword, value })));
     }
 
     fn expression(&self) -> ParserResult<Box<Expr>> {
@@ -107,9 +393,9 @@ impl Parser {
     }
 
     fn assignment(&self) -> ParserResult<Box<Expr>> {
-        let expr = self.equality()?;
+        let expr = self.or()?;
         if self.fits(vec![TokenType::Equal]) {
-            let equals = self.previous();
+            let equals = self.previous().span;
             let value = self.assignment()?;
 
             return match *expr {
@@ -117,18 +403,46 @@ impl Parser {
                     let name = var.name;
                     Ok(Box::new(Expr::Assignment(Assignment::new(name, value))))
                 }
-                _ => Err(ParserError {
-                    line: equals.line,
-                    message: "Invalid assignment target".into(),
-                }),
+                Expr::Get(get) => Ok(Box::new(Expr::Set(Set {
+                    object: get.object,
+                    name: get.name,
+                    value,
+                }))),
+                target => {
+                    self.report(ParserError {
+                        span: equals,
+                        message: "Invalid assignment target.".into(),
+                    });
+                    Ok(Box::new(target))
+                }
             };
         }
         return Ok(expr);
     }
+    fn or(&self) -> ParserResult<Box<Expr>> {
+        let mut expr = self.and()?;
+        while self.fits(vec![TokenType::Or]) {
+            let operator = self.previous().into_owned();
+            let right = self.and()?;
+            expr = Box::new(Expr::Logical(Logical(expr, operator, right)));
+        }
+        return Ok(expr);
+    }
+
+    fn and(&self) -> ParserResult<Box<Expr>> {
+        let mut expr = self.equality()?;
+        while self.fits(vec![TokenType::And]) {
+            let operator = self.previous().into_owned();
+            let right = self.equality()?;
+            expr = Box::new(Expr::Logical(Logical(expr, operator, right)));
+        }
+        return Ok(expr);
+    }
+
     fn equality(&self) -> ParserResult<Box<Expr>> {
         let mut expr = self.comparison()?;
         while self.fits(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = self.previous().clone();
+            let operator = self.previous().into_owned();
             let right = self.comparison()?;
             expr = Box::new(Expr::Binary(Binary(expr, operator, right)));
         }
@@ -143,7 +457,7 @@ impl Parser {
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
-            let operator = self.previous().clone();
+            let operator = self.previous().into_owned();
             let right = self.term()?;
             expr = Box::new(Expr::Binary(Binary(expr, operator, right)));
         }
@@ -152,7 +466,7 @@ impl Parser {
     fn term(&self) -> ParserResult<Box<Expr>> {
         let mut expr = self.factor()?;
         while self.fits(vec![TokenType::Minus, TokenType::Plus]) {
-            let operator = self.previous().clone();
+            let operator = self.previous().into_owned();
             let right = self.factor()?;
             expr = Box::new(Expr::Binary(Binary(expr, operator, right)));
         }
@@ -162,7 +476,7 @@ impl Parser {
     fn factor(&self) -> ParserResult<Box<Expr>> {
         let mut expr = self.unary()?;
         while self.fits(vec![TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous().clone();
+            let operator = self.previous().into_owned();
             let right = self.unary()?;
             expr = Box::new(Expr::Binary(Binary(expr, operator, right)));
         }
@@ -171,48 +485,126 @@ impl Parser {
 
     fn unary(&self) -> ParserResult<Box<Expr>> {
         if self.fits(vec![TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous().clone();
+            let operator = self.previous().into_owned();
             let right = self.unary()?;
             return Ok(Box::new(Expr::Unary(Unary(operator, right))));
         }
-        return self.primary();
+        return self.call();
+    }
+
+    fn call(&self) -> ParserResult<Box<Expr>> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.fits(vec![TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.fits(vec![TokenType::Dot]) {
+                let name = self
+                    .consume(
+                        TokenType::Identifier,
+                        "Expect property name after '.'.".into(),
+                    )?
+                    .into_owned();
+                expr = Box::new(Expr::Get(Get { object: expr, name }));
+            } else {
+                break;
+            }
+        }
+        return Ok(expr);
+    }
+
+    fn finish_call(&self, callee: Box<Expr>) -> ParserResult<Box<Expr>> {
+        let mut arguments = vec![];
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    self.report(ParserError {
+                        span: self.peek().span,
+                        message: format!("Can't have more than {MAX_ARGUMENTS} arguments."),
+                    });
+                }
+                arguments.push(self.expression()?);
+                if !self.fits(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.".into())?
+            .into_owned();
+        return Ok(Box::new(Expr::Call(Call {
+            callee,
+            paren,
+            arguments,
+        })));
     }
 
     fn primary(&self) -> ParserResult<Box<Expr>> {
         if self.fits(vec![TokenType::False]) {
-            return Ok(Box::new(Expr::Literal(Literal::Bool(false))));
+            return Ok(Box::new(self.literal(Literal::Bool(false))));
         }
         if self.fits(vec![TokenType::True]) {
-            return Ok(Box::new(Expr::Literal(Literal::Bool(true))));
+            return Ok(Box::new(self.literal(Literal::Bool(true))));
         }
         if self.fits(vec![TokenType::Nil]) {
-            return Ok(Box::new(Expr::Literal(Literal::Nil)));
+            return Ok(Box::new(self.literal(Literal::Nil)));
         }
         if self.fits(vec![TokenType::String]) {
-            let val = self.previous().literal.clone();
-            return Ok(Box::new(Expr::Literal(Literal::Str(val))));
+            let text = self
+                .interner
+                .borrow()
+                .resolve(self.previous().symbol())
+                .clone();
+            return Ok(Box::new(self.literal(Literal::Str(text))));
         }
         if self.check_is_num() {
             if let Some(TokenType::Number(num)) = self.previous().token_type {
-                return Ok(Box::new(Expr::Literal(Literal::Num(num))));
+                return Ok(Box::new(self.literal(Literal::Num(num))));
             }
         }
         if self.fits(vec![TokenType::LeftParen]) {
+            let start = self.previous().span;
             let expr = self.expression()?;
-            let _ = self.consume(TokenType::RightParen, "Expect ')' after expression.".into());
-            return Ok(Box::new(Expr::Grouping(Grouping(expr))));
+            self.consume(TokenType::RightParen, "Expect ')' after expression.".into())?;
+            let span = start.to(self.previous().span);
+            return Ok(Box::new(Expr::Grouping(Grouping(expr, span))));
+        }
+
+        if self.fits(vec![TokenType::Super]) {
+            let keyword = self.previous().into_owned();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.".into())?;
+            let method = self
+                .consume(
+                    TokenType::Identifier,
+                    "Expect superclass method name.".into(),
+                )?
+                .into_owned();
+            return Ok(Box::new(Expr::Super(Super::new(keyword, method))));
+        }
+
+        if self.fits(vec![TokenType::This]) {
+            return Ok(Box::new(Expr::This(This::new(
+                self.previous().into_owned(),
+            ))));
         }
 
         if self.fits(vec![TokenType::Identifier]) {
             let prev_token = self.previous();
-            return Ok(Box::new(Expr::Variable(Variable {
-                name: prev_token.clone(),
-            })));
+            return Ok(Box::new(Expr::Variable(Variable::new(
+                prev_token.into_owned(),
+            ))));
         }
 
         Err(ParserError {
-            line: 1,
-            message: "Something went wrong".into(),
+            span: self.peek().span,
+            message: "Expect expression.".into(),
+        })
+    }
+
+    // Literal expression located at the token just consumed
+    fn literal(&self, value: Literal) -> Expr {
+        Expr::Literal(LiteralExpr {
+            value,
+            span: self.previous().span,
         })
     }
 
@@ -230,13 +622,11 @@ impl Parser {
         if self.is_at_end() {
             return false;
         }
-        match self.peek().token_type {
-            Some(TokenType::Number(_)) => {
-                self.advance();
-                true
-            }
-            _ => false,
+        let is_num = matches!(self.peek().token_type, Some(TokenType::Number(_)));
+        if is_num {
+            self.advance();
         }
+        is_num
     }
 
     fn check(&self, token_type: TokenType) -> bool {
@@ -245,51 +635,71 @@ impl Parser {
         }
         return self.peek().token_type == Some(token_type);
     }
-    fn advance(&self) -> &Token {
+    fn advance(&self) -> Token<'a> {
         if !self.is_at_end() {
-            let pcurrent = self.current.clone();
-            *pcurrent.borrow_mut() += 1;
+            let next = self.next_token();
+            let current = self.current.replace(next);
+            self.previous.replace(current);
         }
-        return &self.previous();
+        return self.previous();
     }
 
-    fn is_at_end(&self) -> bool {
-        return self.peek().token_type == Some(TokenType::EOF);
+    // Lexical errors are set aside and reported along the parse errors
+    fn next_token(&self) -> Token<'a> {
+        let mut tokens = self.tokens.borrow_mut();
+        loop {
+            match tokens.next() {
+                Some(Ok(mut token)) => {
+                    let named = matches!(
+                        token.token_type,
+                        Some(
+                            TokenType::Identifier
+                                | TokenType::This
+                                | TokenType::Super
+                                | TokenType::String
+                        )
+                    );
+                    if named {
+                        token.symbol = Some(self.interner.borrow_mut().intern(&token.literal));
+                    }
+                    return token;
+                }
+                Some(Err(err)) => self.lex_errors.borrow_mut().push(err),
+                None => unreachable!("The parser stops at the EOF token"),
+            }
+        }
     }
 
-    fn peek(&self) -> &Token {
-        &self.tokens[self.get_current()]
+    fn is_at_end(&self) -> bool {
+        return self.peek().token_type == Some(TokenType::EOF);
     }
 
-    fn get_current(&self) -> usize {
-        *self.current.borrow()
+    fn peek(&self) -> Ref<'_, Token<'a>> {
+        self.current.borrow()
     }
 
-    fn previous(&self) -> &Token {
-        if self.get_current() == 0 {
-            return &self.tokens[0];
-        }
-        &self.tokens[self.get_current() - 1]
+    fn previous(&self) -> Token<'a> {
+        self.previous.borrow().clone()
     }
 
-    fn consume(&self, token_type: TokenType, message: String) -> ParserResult<&Token> {
+    fn consume(&self, token_type: TokenType, message: String) -> ParserResult<Token<'a>> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
-        let line_num = self.peek().line;
         Err(ParserError {
-            line: line_num,
+            span: self.peek().span,
             message,
         })
     }
 
-    fn synchronize(&self) -> () {
+    fn synchronize(&self) {
         let _ = self.advance();
         while !self.is_at_end() {
             if self.previous().token_type == Some(TokenType::Semicolon) {
                 return;
             }
-            match self.peek().token_type {
+            let token_type = self.peek().token_type.clone();
+            match token_type {
                 Some(
                     TokenType::Class
                     | TokenType::Fun
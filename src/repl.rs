@@ -0,0 +1,152 @@
+use std::{fs, path::PathBuf};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use lox_rs::{LexError, LexErrorKind, Lox, Scanner, TokenType};
+
+use crate::{Args, JloxResult};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = ". ";
+const HISTORY_FILE: &str = ".rslox_history";
+
+// Interactive session, lines are buffered until brackets are balanced and
+// the value of a bare expression is printed back
+pub fn run_prompt(args: &Args) -> JloxResult {
+    let mut args = args.clone();
+    let mut lox = args.session();
+    let mut editor = DefaultEditor::new()?;
+    let history = history_path();
+    if let Some(history) = &history {
+        // There is no history the first time
+        let _ = editor.load_history(history);
+    }
+
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        match editor.readline(prompt) {
+            // Commands are only recognised at the start of a statement
+            Ok(line) if input.is_empty() && line.trim_start().starts_with(':') => {
+                editor.add_history_entry(line.trim())?;
+                command(line.trim(), &mut args, &mut lox);
+                continue;
+            }
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+            }
+            // Ctrl-C drops whatever was typed so far
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        }
+        if is_incomplete(&input) {
+            continue;
+        }
+
+        editor.add_history_entry(input.trim_end())?;
+        match lox.eval_str(&input) {
+            Ok(Some(value)) => println!("{value}"),
+            Ok(None) => {}
+            Err(err) => lox.report(&err, "<stdin>", &input)?,
+        }
+        input.clear();
+    }
+
+    if let Some(history) = &history {
+        editor.save_history(history)?;
+    }
+    Ok(())
+}
+
+const HELP: &str = "\
+:env            list the global bindings
+:tokens         toggle printing the tokens of each line
+:ast            toggle printing the ast of each line
+:load <file>    run a script into the current session
+:reset          drop every global defined in the session
+:type <expr>    show the type of an expression
+:help           show this message";
+
+fn command(line: &str, args: &mut Args, lox: &mut Lox) {
+    let (name, argument) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(name, argument)| (name, argument.trim()));
+    match (name, argument) {
+        (":env", "") => {
+            for (name, value) in lox.globals() {
+                println!("{name} = {value}");
+            }
+        }
+        (":tokens", "") => {
+            args.print_tokens = !args.print_tokens;
+            lox.set_print_tokens(args.print_tokens);
+            println!("tokens {}", on_off(args.print_tokens));
+        }
+        (":ast", "") => {
+            args.print_ast = !args.print_ast;
+            lox.set_print_ast(args.print_ast);
+            println!("ast {}", on_off(args.print_ast));
+        }
+        (":load", path) if !path.is_empty() => match fs::read_to_string(path) {
+            Ok(source) => {
+                if let Err(err) = lox.eval_str(&source) {
+                    let _ = lox.report(&err, path, &source);
+                }
+            }
+            Err(err) => eprintln!("error: can't read '{path}': {err}"),
+        },
+        (":reset", "") => *lox = args.session(),
+        (":type", expr) if !expr.is_empty() => match lox.eval_expr(expr) {
+            Ok(value) => println!("{}", value.type_name()),
+            Err(err) => {
+                let _ = lox.report(&err, "<stdin>", expr);
+            }
+        },
+        (":help", "") => println!("{HELP}"),
+        _ => eprintln!("error: unknown command '{line}', see ':help'"),
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::home_dir().map(|home| home.join(HISTORY_FILE))
+}
+
+// More lines are needed while brackets are left open, or a string or
+// block comment isn't terminated
+fn is_incomplete(source: &str) -> bool {
+    let mut depth: usize = 0;
+    for token in Scanner::new(source) {
+        match token {
+            Ok(token) => match token.token_type {
+                Some(TokenType::LeftParen | TokenType::LeftBrace) => depth += 1,
+                Some(TokenType::RightParen | TokenType::RightBrace) => {
+                    depth = depth.saturating_sub(1);
+                }
+                _ => {}
+            },
+            Err(LexError {
+                kind: LexErrorKind::UnterminatedString | LexErrorKind::UnterminatedComment,
+                ..
+            }) => return true,
+            Err(_) => {}
+        }
+    }
+    depth > 0
+}
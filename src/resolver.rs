@@ -0,0 +1,328 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    error, fmt,
+    rc::Rc,
+};
+
+use crate::{
+    ast::{
+        Assignment, Binary, Call, Expr, Get, Grouping, LiteralExpr, Logical, Set, Super, This,
+        Unary, Variable, VisitExpr,
+    },
+    intern::Symbol,
+    scanner::{Span, Token},
+    statement::{Block, Class, Function, If, PrintStmt, Return, Stmt, Var, VisitStmt, While},
+};
+
+#[derive(Debug)]
+pub struct ResolverError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for ResolverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+impl error::Error for ResolverError {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// Static pass run between the parser and the interpreter.
+// Each local variable use gets the number of scopes between it and its
+// declaration, globals are left unresolved and looked up dynamically.
+pub struct Resolver {
+    // `false` while the variable is declared but its initializer
+    // hasn't been resolved yet
+    scopes: RefCell<Vec<HashMap<Symbol, bool>>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+    errors: RefCell<Vec<ResolverError>>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: RefCell::new(vec![]),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+            errors: RefCell::new(vec![]),
+        }
+    }
+
+    // Like the parser, resolution goes on after an error to report them
+    // all, in source order
+    pub fn resolve(&self, stmts: &[Box<Stmt>]) -> Result<(), Vec<ResolverError>> {
+        self.resolve_stmts(stmts);
+        let errors = self.errors.take();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        return Ok(());
+    }
+
+    fn resolve_stmts(&self, stmts: &[Box<Stmt>]) {
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+    }
+
+    fn resolve_expr(&self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn error(&self, span: Span, message: &str) {
+        self.errors.borrow_mut().push(ResolverError {
+            span,
+            message: message.into(),
+        });
+    }
+
+    fn resolve_local(&self, name: &Token, depth: &Cell<Option<usize>>) {
+        let scopes = self.scopes.borrow();
+        for (distance, scope) in scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.symbol()) {
+                depth.set(Some(distance));
+                return;
+            }
+        }
+    }
+
+    fn resolve_function(&self, function: &Function, function_type: FunctionType) {
+        let enclosing_function = self.current_function.replace(function_type);
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(&function.body);
+        self.end_scope();
+        self.current_function.set(enclosing_function);
+    }
+
+    fn resolve_class(&self, stmt: &Class) {
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+
+        // Subclass methods are resolved inside an extra scope binding `super`
+        if let Some(superclass) = &stmt.superclass {
+            if superclass.name.symbol() == stmt.name.symbol() {
+                self.error(superclass.name.span, "A class can't inherit from itself.");
+            }
+            self.current_class.set(ClassType::Subclass);
+            self.visit_variable(superclass);
+            self.begin_scope();
+            self.bind_keyword(Symbol::SUPER);
+        }
+
+        // Methods are resolved inside a scope that binds `this`
+        self.begin_scope();
+        self.bind_keyword(Symbol::THIS);
+        for method in &stmt.methods {
+            let function_type = if method.name.symbol() == Symbol::INIT {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+        self.end_scope();
+
+        if stmt.superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn bind_keyword(&self, keyword: Symbol) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(keyword, true);
+        }
+    }
+
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn declare(&self, name: &Token) {
+        let mut scopes = self.scopes.borrow_mut();
+        let Some(scope) = scopes.last_mut() else {
+            return;
+        };
+        if scope.contains_key(&name.symbol()) {
+            self.error(
+                name.span,
+                "Already a variable with this name in this scope.",
+            );
+            return;
+        }
+        scope.insert(name.symbol(), false);
+    }
+
+    fn define(&self, name: &Token) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name.symbol(), true);
+        }
+    }
+}
+
+impl VisitStmt<()> for Resolver {
+    fn visit_expr_stmt(&self, stmt: &Expr) {
+        self.resolve_expr(stmt);
+    }
+
+    fn visit_print_stmt(&self, stmt: &PrintStmt) {
+        self.resolve_expr(&stmt.1);
+    }
+
+    fn visit_var_stmt(&self, stmt: &Var) {
+        self.declare(&stmt.name);
+        if let Some(initializer) = &stmt.initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(&stmt.name);
+    }
+
+    fn visit_block_stmt(&self, stmt: &Block) {
+        self.begin_scope();
+        self.resolve_stmts(&stmt.0);
+        self.end_scope();
+    }
+
+    fn visit_if_stmt(&self, stmt: &If) {
+        self.resolve_expr(&stmt.condition);
+        stmt.then_branch.accept(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_while_stmt(&self, stmt: &While) {
+        self.resolve_expr(&stmt.condition);
+        stmt.body.accept(self);
+    }
+
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) {
+        // Defined before the body so the function can recurse
+        self.declare(&stmt.name);
+        self.define(&stmt.name);
+        self.resolve_function(stmt, FunctionType::Function);
+    }
+
+    fn visit_return_stmt(&self, stmt: &Return) {
+        if self.current_function.get() == FunctionType::None {
+            self.error(stmt.keyword.span, "Can't return from top-level code.");
+        }
+        if let Some(value) = &stmt.value {
+            if self.current_function.get() == FunctionType::Initializer {
+                self.error(
+                    stmt.keyword.span,
+                    "Can't return a value from an initializer.",
+                );
+            }
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_class_stmt(&self, stmt: &Class) {
+        let enclosing_class = self.current_class.replace(ClassType::Class);
+        self.resolve_class(stmt);
+        self.current_class.set(enclosing_class);
+    }
+}
+
+impl VisitExpr<()> for Resolver {
+    fn visit_binary(&self, expr: &Binary) {
+        self.resolve_expr(&expr.0);
+        self.resolve_expr(&expr.2);
+    }
+
+    fn visit_literal(&self, _: &LiteralExpr) {}
+
+    fn visit_unary(&self, expr: &Unary) {
+        self.resolve_expr(&expr.1);
+    }
+
+    fn visit_grouping(&self, expr: &Grouping) {
+        self.resolve_expr(&expr.0);
+    }
+
+    fn visit_variable(&self, expr: &Variable) {
+        let declared_only = self
+            .scopes
+            .borrow()
+            .last()
+            .and_then(|scope| scope.get(&expr.name.symbol()))
+            == Some(&false);
+        if declared_only {
+            self.error(
+                expr.name.span,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+        self.resolve_local(&expr.name, &expr.depth);
+    }
+
+    fn visit_assignment(&self, expr: &Assignment) {
+        self.resolve_expr(&expr.value);
+        self.resolve_local(&expr.name, &expr.depth);
+    }
+
+    fn visit_logical(&self, expr: &Logical) {
+        self.resolve_expr(&expr.0);
+        self.resolve_expr(&expr.2);
+    }
+
+    fn visit_call(&self, expr: &Call) {
+        self.resolve_expr(&expr.callee);
+        for argument in &expr.arguments {
+            self.resolve_expr(argument);
+        }
+    }
+
+    fn visit_get(&self, expr: &Get) {
+        self.resolve_expr(&expr.object);
+    }
+
+    fn visit_set(&self, expr: &Set) {
+        self.resolve_expr(&expr.value);
+        self.resolve_expr(&expr.object);
+    }
+
+    fn visit_this(&self, expr: &This) {
+        if self.current_class.get() == ClassType::None {
+            self.error(expr.keyword.span, "Can't use 'this' outside of a class.");
+        }
+        self.resolve_local(&expr.keyword, &expr.depth);
+    }
+
+    fn visit_super(&self, expr: &Super) {
+        let message = match self.current_class.get() {
+            ClassType::None => "Can't use 'super' outside of a class.",
+            ClassType::Class => "Can't use 'super' in a class with no superclass.",
+            ClassType::Subclass => {
+                self.resolve_local(&expr.keyword, &expr.depth);
+                return;
+            }
+        };
+        self.error(expr.keyword.span, message);
+    }
+}
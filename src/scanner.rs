@@ -1,48 +1,34 @@
 use multipeek::{multipeek, MultiPeek};
 use nom::number::complete::double;
 use nom::IResult;
-use std::iter::Enumerate;
-use std::str::Chars;
+use std::borrow::Cow;
+use std::str::CharIndices;
+use std::{error, fmt};
+use unicode_ident::{is_xid_continue, is_xid_start};
 
-use crate::JLoxError;
+use crate::intern::Symbol;
 
 type NumberMetadata = f64;
 
-#[derive(Debug)]
-struct TokenInfo {
-    token_type: TokenType,
-    literal: String,
-    new_lines: usize,
-}
-
-impl TokenInfo {
-    fn new(token_type: TokenType, literal: String, new_lines: usize) -> Self {
-        Self {
-            token_type,
-            literal,
-            new_lines,
-        }
-    }
-    fn get(keyword: &str) -> TokenType {
-        match keyword {
-            "and" => TokenType::And,
-            "class" => TokenType::Class,
-            "else" => TokenType::Else,
-            "false" => TokenType::False,
-            "for" => TokenType::For,
-            "fun" => TokenType::Fun,
-            "if" => TokenType::If,
-            "nil" => TokenType::Nil,
-            "or" => TokenType::Or,
-            "print" => TokenType::Print,
-            "return" => TokenType::Return,
-            "super" => TokenType::Super,
-            "this" => TokenType::This,
-            "true" => TokenType::True,
-            "var" => TokenType::Var,
-            "while" => TokenType::While,
-            _ => TokenType::Identifier,
-        }
+fn keyword(text: &str) -> TokenType {
+    match text {
+        "and" => TokenType::And,
+        "class" => TokenType::Class,
+        "else" => TokenType::Else,
+        "false" => TokenType::False,
+        "for" => TokenType::For,
+        "fun" => TokenType::Fun,
+        "if" => TokenType::If,
+        "nil" => TokenType::Nil,
+        "or" => TokenType::Or,
+        "print" => TokenType::Print,
+        "return" => TokenType::Return,
+        "super" => TokenType::Super,
+        "this" => TokenType::This,
+        "true" => TokenType::True,
+        "var" => TokenType::Var,
+        "while" => TokenType::While,
+        _ => TokenType::Identifier,
     }
 }
 
@@ -95,273 +81,541 @@ pub enum TokenType {
     EOF,
 }
 
+// Location of a piece of source code. `offset` and `len` are in bytes,
+// `line` and `column` start at 1 and `column` counts characters.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    // Smallest span covering both `self` and `other`
+    #[must_use]
+    pub fn to(self, other: Span) -> Span {
+        let (start, end) = if self.offset <= other.offset {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        Span {
+            len: (end.offset + end.len).max(start.offset + start.len) - start.offset,
+            ..start
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexErrorKind {
+    UnterminatedString,
+    UnterminatedComment,
+    UnexpectedCharacter(char),
+    MalformedNumber(String),
+    // The offending sequence, backslash included
+    InvalidEscape(String),
+}
+
+// Lexical error, the scanner skips the offending characters and goes on
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl LexError {
+    #[must_use]
+    pub fn message(&self) -> String {
+        match &self.kind {
+            LexErrorKind::UnterminatedString => "Unterminated string.".into(),
+            LexErrorKind::UnterminatedComment => "Unterminated block comment.".into(),
+            LexErrorKind::UnexpectedCharacter(_) => "Unexpected character.".into(),
+            LexErrorKind::MalformedNumber(reason) => format!("Malformed number, {reason}."),
+            LexErrorKind::InvalidEscape(sequence) => {
+                format!("Invalid escape sequence '{sequence}'.")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message())
+    }
+}
+impl error::Error for LexError {}
+
+// Lexemes borrow from the source while scanning, the parser only makes
+// owned copies of the tokens it keeps in the AST
 #[derive(Default, Debug, Clone)]
-pub struct Token {
+pub struct Token<'a> {
     pub token_type: Option<TokenType>,
-    pub lexeme: String,
-    pub literal: String,
-    pub line: usize,
+    pub lexeme: Cow<'a, str>,
+    // String contents without the quotes, the lexeme for anything else
+    pub literal: Cow<'a, str>,
+    pub span: Span,
+    // Interned `literal` of names and strings, set by the parser
+    pub(crate) symbol: Option<Symbol>,
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
-        format!("{:?}  {}  {:?}", self.token_type, self.lexeme, self.literal)
+impl Token<'_> {
+    #[must_use]
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            token_type: self.token_type,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            literal: Cow::Owned(self.literal.into_owned()),
+            span: self.span,
+            symbol: self.symbol,
+        }
+    }
+
+    pub(crate) fn symbol(&self) -> Symbol {
+        self.symbol.expect("The parser interns the names it keeps")
     }
 }
-pub struct Scanner {
-    source: String,
-    pub tokens: Vec<Token>,
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}  {}  {:?}",
+            self.token_type, self.lexeme, self.literal
+        )
+    }
+}
+
+// Produces the tokens lazily, lexical errors are yielded in place of the
+// offending characters and scanning goes on after them. The last item is
+// always an EOF token.
+pub struct Scanner<'a> {
+    source: &'a str,
+    chars: MultiPeek<CharIndices<'a>>,
     line: usize,
-    current: usize,
+    // Column of the next character, counted in characters
+    column: usize,
+    // Where the current token starts
+    start: usize,
+    start_line: usize,
+    start_column: usize,
+    // Contents of the last string literal, only when it had escapes
+    unescaped: Option<String>,
+    // Token to yield right after an error
+    queued: Option<Token<'a>>,
+    finished: bool,
 }
 
-impl Scanner {
-    pub fn new(source: String) -> Self {
+impl<'a> Scanner<'a> {
+    #[must_use]
+    pub fn new(source: &'a str) -> Self {
         Self {
             source,
-            tokens: Vec::new(),
+            chars: multipeek(source.char_indices()),
             line: 1,
-            current: 0,
+            column: 1,
+            start: 0,
+            start_line: 1,
+            start_column: 1,
+            unescaped: None,
+            queued: None,
+            finished: false,
         }
     }
-    pub fn scan_tokens(&mut self) -> Result<(), JLoxError> {
-        // let mut tokens = Vec::new();
-        let mut iter = multipeek(self.source.chars().enumerate());
-        let mut current_token: Vec<char> = Vec::new();
-        while let Some((current, symbol)) = iter.next() {
-            self.current = current;
-            if symbol == '\n' {
-                self.line += 1;
-                // No need for now to do anything else if we know is a new line
-                continue;
-            }
-            let scan_result = self.scan_token(symbol, &mut iter, &mut current_token);
-
-            match scan_result {
-                Ok(Some(token_info)) => {
-
-                    let token = Token {
-                        token_type: Some(token_info.token_type),
-                        lexeme: String::from_iter(current_token.drain(..)),
-                        literal: token_info.literal,
-                        line: self.line,
-                    };
-                    self.line += token_info.new_lines;
-                    self.tokens.push(token)
-                }
-                Ok(None) => {
-                    current_token.drain(..);
-                    continue;
-                }
-                Err(err) => {
-                    eprintln!("{}", err);
-                }
-            }
+
+    // Byte offset of the next character
+    fn position(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.source.len(), |(offset, _)| *offset)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, symbol)| *symbol)
+    }
+
+    fn peek_next(&mut self) -> Option<char> {
+        self.chars.peek_nth(1).map(|(_, symbol)| *symbol)
+    }
+
+    // Consumes the next character, keeping track of the lines and columns
+    fn advance(&mut self) -> Option<char> {
+        let (_, symbol) = self.chars.next()?;
+        if symbol == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
         }
-        let t = Token {
-            token_type: Some(TokenType::EOF),
-            lexeme: String::from(""),
-            literal: String::new(),
+        Some(symbol)
+    }
+
+    // Consumes the next character when it's `expected`
+    fn matches(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    // Span from the start of the current token up to the next character
+    fn token_span(&mut self) -> Span {
+        let end = self.position();
+        Span {
+            offset: self.start,
+            len: end - self.start,
+            line: self.start_line,
+            column: self.start_column,
+        }
+    }
+
+    // Span from `start`, which must be in the current line, up to the next
+    // character. Only the characters since `start` are counted for the column.
+    fn span_from(&mut self, start: usize) -> Span {
+        let end = self.position();
+        Span {
+            offset: start,
+            len: end - start,
             line: self.line,
+            column: self.column - self.source[start..end].chars().count(),
+        }
+    }
+
+    fn error(&mut self, kind: LexErrorKind) -> LexError {
+        LexError {
+            kind,
+            span: self.token_span(),
+        }
+    }
+
+    // Token for the characters scanned since `start`
+    fn token(&mut self, token_type: TokenType) -> Token<'a> {
+        let span = self.token_span();
+        let lexeme = &self.source[span.offset..span.offset + span.len];
+        let literal = match (&token_type, self.unescaped.take()) {
+            (TokenType::String, Some(unescaped)) => Cow::Owned(unescaped),
+            (TokenType::String, None) => Cow::Borrowed(&lexeme[1..lexeme.len() - 1]),
+            _ => Cow::Borrowed(lexeme),
         };
+        Token {
+            token_type: Some(token_type),
+            lexeme: Cow::Borrowed(lexeme),
+            literal,
+            span,
+            symbol: None,
+        }
+    }
 
-        self.tokens.push(t);
-        return Ok(());
+    // Returning Ok(None) means the characters didn't make a token
+    fn scan_token(&mut self, symbol: char) -> Result<Option<TokenType>, LexError> {
+        let token_type = match symbol {
+            '"' => self.string()?,
+            '0'..='9' => self.number(symbol)?,
+            symbol if symbol == '_' || is_xid_start(symbol) => self.identifier(),
+            '(' => TokenType::LeftParen,
+            ')' => TokenType::RightParen,
+            '{' => TokenType::LeftBrace,
+            '}' => TokenType::RightBrace,
+            ',' => TokenType::Comma,
+            '.' => TokenType::Dot,
+            '-' => TokenType::Minus,
+            '+' => TokenType::Plus,
+            ';' => TokenType::Semicolon,
+            '*' => TokenType::Star,
+            '!' if self.matches('=') => TokenType::BangEqual,
+            '!' => TokenType::Bang,
+            '=' if self.matches('=') => TokenType::EqualEqual,
+            '=' => TokenType::Equal,
+            '<' if self.matches('=') => TokenType::LessEqual,
+            '<' => TokenType::Less,
+            '>' if self.matches('=') => TokenType::GreaterEqual,
+            '>' => TokenType::Greater,
+            '/' if self.matches('/') => {
+                // Comments go until the end of the line
+                while self.peek().is_some_and(|next| next != '\n') {
+                    self.advance();
+                }
+                return Ok(None);
+            }
+            '/' if self.matches('*') => {
+                self.block_comment()?;
+                return Ok(None);
+            }
+            '/' => TokenType::Slash,
+            ' ' | '\r' | '\t' | '\n' => return Ok(None),
+            _ => return Err(self.error(LexErrorKind::UnexpectedCharacter(symbol))),
+        };
+        Ok(Some(token_type))
     }
 
-    fn scan_token(
-        &self,
-        symbol: char,
-        iter: &mut MultiPeek<Enumerate<Chars>>,
-        current_token: &mut Vec<char>
-    ) -> Result<Option<TokenInfo>, JLoxError> {
-        // Returning Ok(None) means keep advancing
-        current_token.push(symbol);
-        match symbol {
-            '"' => {
-                // String
-                let mut value = String::new();
-                let mut new_lines_count: usize = 0;
-                loop {
-                    match iter.peek() {
-                        Some((_, '"')) => {
-                            // When it's new line break
-                            // iter.next();
-                            current_token.push('"');
-                            break;
-                        }
-                        Some((_, '\n')) => {
-                            // TODO: move line +1
-                            new_lines_count += 1;
-                            value.push('\n');
-                            current_token.push('\n');
-                            iter.next();
-                        }
-                        Some((_, char)) => {
-                            value.push(*char);
-                            current_token.push(*char);
-                            iter.next();
-                        }
-                        None => {
-                            return Err(JLoxError(self.line, "Unterminated string".into()));
-                        }
-                    }
+    // `/* ... */`, comments inside it must be closed too
+    fn block_comment(&mut self) -> Result<(), LexError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if self.matches('*') => depth += 1,
+                Some('*') if self.matches('/') => depth -= 1,
+                Some(_) => {}
+                None => {
+                    // Reported at the opening `/*`
+                    return Err(LexError {
+                        kind: LexErrorKind::UnterminatedComment,
+                        span: Span {
+                            offset: self.start,
+                            len: 2,
+                            line: self.start_line,
+                            column: self.start_column,
+                        },
+                    });
                 }
-                iter.next();
-                Ok(Some(TokenInfo::new(
-                    TokenType::String,
-                    value,
-                    new_lines_count,
-                )))
             }
-            '0'..='9' => {
-                // numbers
-                let mut literal = String::from(symbol);
-                loop {
-                    match iter.peek() {
-                        Some((_, '0'..='9')) => {
-                            let next_char = iter.next();
-                            if let Some((_, ch)) = next_char {
-                                current_token.push(ch);
-                                literal.push(ch);
-                            }
-                        }
-                        Some((_, '.')) => {
-                            let peeked = iter.peek_nth(1);
-
-                            if let Some((_, '0'..='9')) = peeked {
-                                let next_char = iter.next();
-                                if let Some((_, ch)) = next_char {
-                                    current_token.push(ch);
-                                    literal.push(ch);
-                                }
-                            } else {
-                                return Err(JLoxError(
-                                    self.line,
-                                    "Error at end: Expect property name after '.'".into(),
-                                ));
-                            }
-                        }
-                        _ => {
-                            // iter.next();
-                            break;
+        }
+        Ok(())
+    }
+
+    // The contents are only copied when there are escape sequences,
+    // after an invalid one the string is still consumed to its end
+    fn string(&mut self) -> Result<TokenType, LexError> {
+        let content_start = self.position();
+        let mut unescaped: Option<String> = None;
+        let mut error = None;
+        loop {
+            let offset = self.position();
+            match self.advance() {
+                Some('"') => break,
+                Some('\\') => {
+                    let value = unescaped
+                        .get_or_insert_with(|| self.source[content_start..offset].to_string());
+                    match self.escape(offset) {
+                        Ok(symbol) => value.push(symbol),
+                        Err(err) => {
+                            error.get_or_insert(err);
                         }
                     }
                 }
-                let parsed: IResult<_, _> = double(literal.as_str());
-                match parsed {
-                    Ok((_, number)) => {
-                        return Ok(Some(TokenInfo::new(TokenType::Number(number), literal, 0)));
-                    }
-                    Err(error) => {
-                        return Err(JLoxError(
-                            self.line,
-                            format!("Problem parsing: {:?}", error),
-                        ))
+                Some(symbol) => {
+                    if let Some(value) = &mut unescaped {
+                        value.push(symbol);
                     }
                 }
+                None => return Err(self.error(LexErrorKind::UnterminatedString)),
             }
-            'a'..='z' | '_' | 'A'..='Z' => {
-                let mut keyword = String::from(symbol);
-                loop {
-                    match iter.peek() {
-                        Some((_, '1'..='9' | '_' | 'a'..='z' | 'A'..='Z')) => {
-                            let next_char = iter.next();
-                            if let Some((_, ch)) = next_char {
-                                current_token.push(ch);
-                                keyword.push(ch);
-                            }
-                        }
-                        _ => {
-                            iter.next();
-                            break;
-                        }
-                    }
-                }
-                let token_type = TokenInfo::get(&keyword);
-                Ok(Some(TokenInfo::new(token_type, keyword, 0)))
+        }
+        self.unescaped = unescaped;
+        match error {
+            Some(err) => Err(err),
+            None => Ok(TokenType::String),
+        }
+    }
+
+    // `start` is the offset of the backslash
+    fn escape(&mut self, start: usize) -> Result<char, LexError> {
+        let symbol = match self.peek() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('u') => {
+                self.advance();
+                return self.unicode_escape(start);
             }
-            '(' => Ok(Some(TokenInfo::new(TokenType::LeftParen, symbol.into(), 0))),
-            ')' => Ok(Some(TokenInfo::new(
-                TokenType::RightParen,
-                symbol.into(),
-                0,
-            ))),
-            '{' => Ok(Some(TokenInfo::new(TokenType::LeftBrace, symbol.into(), 0))),
-
-            '}' => Ok(Some(TokenInfo::new(
-                TokenType::RightBrace,
-                symbol.into(),
-                0,
-            ))),
-            ',' => Ok(Some(TokenInfo::new(TokenType::Comma, symbol.into(), 0))),
-            '.' => Ok(Some(TokenInfo::new(TokenType::Dot, symbol.into(), 0))),
-            '-' => Ok(Some(TokenInfo::new(TokenType::Minus, symbol.into(), 0))),
-            '+' => Ok(Some(TokenInfo::new(TokenType::Plus, symbol.into(), 0))),
-            ';' => Ok(Some(TokenInfo::new(TokenType::Semicolon, symbol.into(), 0))),
-            '*' => Ok(Some(TokenInfo::new(TokenType::Star, symbol.into(), 0))),
-            '!' => match iter.peek() {
-                Some((_, '=')) => {
-                    iter.next();
-                    Ok(Some(TokenInfo::new(TokenType::BangEqual, symbol.into(), 0)))
-                }
-                _ => Ok(Some(TokenInfo::new(TokenType::Bang, symbol.into(), 0))),
-            },
-            '=' => match iter.peek() {
-                Some((_, '=')) => {
-                    iter.next();
-                    Ok(Some(TokenInfo::new(
-                        TokenType::EqualEqual,
-                        symbol.into(),
-                        0,
-                    )))
-                }
-                _ => Ok(Some(TokenInfo::new(TokenType::Equal, symbol.into(), 0))),
-            },
-            '<' => match iter.peek() {
-                Some((_, '=')) => {
-                    iter.next();
-                    Ok(Some(TokenInfo::new(TokenType::LessEqual, symbol.into(), 0)))
-                }
-                _ => Ok(Some(TokenInfo::new(TokenType::Less, symbol.into(), 0))),
-            },
-            '>' => match iter.peek() {
-                Some((_, '=')) => {
-                    iter.next();
-                    Ok(Some(TokenInfo::new(
-                        TokenType::GreaterEqual,
-                        symbol.into(),
-                        0,
-                    )))
+            // A backslash ending the line or the source escapes nothing
+            Some('\n') | None => {
+                return Err(LexError {
+                    kind: LexErrorKind::InvalidEscape("\\".into()),
+                    span: self.span_from(start),
+                })
+            }
+            Some(_) => {
+                self.advance();
+                let sequence = self.source[start..self.position()].to_string();
+                return Err(LexError {
+                    kind: LexErrorKind::InvalidEscape(sequence),
+                    span: self.span_from(start),
+                });
+            }
+        };
+        self.advance();
+        Ok(symbol)
+    }
+
+    // `\u{...}` with 1 to 6 hexadecimal digits naming a Unicode scalar value
+    fn unicode_escape(&mut self, start: usize) -> Result<char, LexError> {
+        let mut digits = String::new();
+        let closed = if self.matches('{') {
+            while let Some(digit) = self.peek().filter(char::is_ascii_hexdigit) {
+                digits.push(digit);
+                self.advance();
+            }
+            self.matches('}')
+        } else {
+            false
+        };
+        let symbol = u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| closed && digits.len() <= 6)
+            .and_then(char::from_u32);
+        symbol.ok_or_else(|| {
+            let span = self.span_from(start);
+            LexError {
+                kind: LexErrorKind::InvalidEscape(
+                    self.source[start..span.offset + span.len].to_string(),
+                ),
+                span,
+            }
+        })
+    }
+
+    // `123`, `1_000.5` and `6.02e23`, or `0x1F`, `0b1010` and `0o17`.
+    // A `.` not followed by a digit isn't part of the number.
+    fn number(&mut self, first: char) -> Result<TokenType, LexError> {
+        let radix = match (first, self.peek()) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('b' | 'B')) => 2,
+            ('0', Some('o' | 'O')) => 8,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.advance();
+            return self.prefixed_number(radix);
+        }
+
+        self.digits(10)?;
+        if self.peek() == Some('.') && self.peek_next().is_some_and(|next| next.is_ascii_digit()) {
+            self.advance();
+            self.digits(10)?;
+        }
+        if let Some('e' | 'E') = self.peek() {
+            self.advance();
+            if let Some('+' | '-') = self.peek() {
+                self.advance();
+            }
+            if !self.peek().is_some_and(|next| next.is_ascii_digit()) {
+                return Err(self.malformed_number("expect digits in the exponent".into()));
+            }
+            self.digits(10)?;
+        }
+        let end = self.position();
+        let literal: String = self.source[self.start..end]
+            .chars()
+            .filter(|symbol| *symbol != '_')
+            .collect();
+        let parsed: IResult<_, _> = double(literal.as_str());
+        match parsed {
+            Ok((_, number)) => Ok(TokenType::Number(number)),
+            Err(error) => Err(self.malformed_number(format!("{error:?}"))),
+        }
+    }
+
+    // Digits after a `0x`, `0b` or `0o` prefix
+    fn prefixed_number(&mut self, radix: u32) -> Result<TokenType, LexError> {
+        let prefix = &self.source[self.start..self.position()];
+        if !self.peek().is_some_and(|next| next.is_digit(radix)) {
+            return Err(self.malformed_number(format!("expect digits after '{prefix}'")));
+        }
+        let digits_start = self.position();
+        self.digits(radix)?;
+        let digits_end = self.position();
+        // Something like the `2` in `0b102`
+        if let Some(invalid) = self.peek().filter(char::is_ascii_alphanumeric) {
+            while self.peek().is_some_and(|next| next.is_ascii_alphanumeric()) {
+                self.advance();
+            }
+            return Err(self.malformed_number(format!(
+                "invalid digit '{invalid}' in a base {radix} literal"
+            )));
+        }
+        let number = self.source[digits_start..digits_end]
+            .chars()
+            .filter_map(|digit| digit.to_digit(radix))
+            .fold(0.0, |number, digit| {
+                number * f64::from(radix) + f64::from(digit)
+            });
+        Ok(TokenType::Number(number))
+    }
+
+    // Consumes digits of `radix`, `_` can separate them but not end them
+    fn digits(&mut self, radix: u32) -> Result<(), LexError> {
+        let mut last = None;
+        while let Some(next) = self
+            .peek()
+            .filter(|next| next.is_digit(radix) || *next == '_')
+        {
+            last = Some(next);
+            self.advance();
+        }
+        if last == Some('_') {
+            return Err(self.malformed_number("'_' must be followed by a digit".into()));
+        }
+        Ok(())
+    }
+
+    fn malformed_number(&mut self, reason: String) -> LexError {
+        self.error(LexErrorKind::MalformedNumber(reason))
+    }
+
+    // Identifiers follow the Unicode XID rules, plus a leading `_`
+    fn identifier(&mut self) -> TokenType {
+        while self.peek().is_some_and(is_xid_continue) {
+            self.advance();
+        }
+        let end = self.position();
+        keyword(&self.source[self.start..end])
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Result<Token<'a>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.queued.take() {
+            return Some(Ok(token));
+        }
+        if self.finished {
+            return None;
+        }
+        loop {
+            self.start = self.position();
+            self.start_line = self.line;
+            self.start_column = self.column;
+            let Some(symbol) = self.advance() else {
+                self.finished = true;
+                return Some(Ok(self.token(TokenType::EOF)));
+            };
+            let token_type = match self.scan_token(symbol) {
+                Ok(Some(token_type)) => token_type,
+                Ok(None) => continue,
+                // The string itself is fine, yielding it spares the parser
+                // from reporting a missing expression
+                Err(
+                    err @ LexError {
+                        kind: LexErrorKind::InvalidEscape(_),
+                        ..
+                    },
+                ) => {
+                    self.queued = Some(self.token(TokenType::String));
+                    return Some(Err(err));
                 }
-                _ => Ok(Some(TokenInfo::new(TokenType::Greater, symbol.into(), 0))),
-            },
-            '/' => match iter.peek() {
-                Some((_, '/')) => {
-                    loop {
-                        match iter.peek() {
-                            Some((_, '\n')) => {
-                                // When it's new line break
-                                break;
-                            }
-                            None => {
-                                break;
-                            }
-                            _ => {
-                                // Anything else is a comment
-                                iter.next();
-                            }
-                        }
-                    }
-                    Ok(None)
+                // Same for a number, its value doesn't matter anymore
+                Err(
+                    err @ LexError {
+                        kind: LexErrorKind::MalformedNumber(_),
+                        ..
+                    },
+                ) => {
+                    self.queued = Some(self.token(TokenType::Number(0.0)));
+                    return Some(Err(err));
                 }
-                _ => Ok(Some(TokenInfo::new(TokenType::Slash, symbol.into(), 0))),
-            },
-            ' ' | '\r' | '\t' => Ok(None),
-            _ => Err(JLoxError(
-                self.line,
-                format!("Unexpected character {symbol}").into(),
-            )),
+                Err(err) => return Some(Err(err)),
+            };
+            return Some(Ok(self.token(token_type)));
         }
     }
 }
@@ -0,0 +1,72 @@
+use std::{
+    cell::RefCell,
+    io::{self, IsTerminal, Write},
+    rc::Rc,
+};
+
+/// Where the text printed by scripts, or the diagnostics they raise, goes.
+pub enum Sink {
+    Stdout,
+    Stderr,
+    /// Any writer, e.g. a file or a socket.
+    Writer(Box<dyn Write>),
+    /// Bytes shared with the host, which reads them back, see [`Sink::buffer`].
+    Buffer(Rc<RefCell<Vec<u8>>>),
+    /// Receives each piece of text as it's written.
+    Callback(Box<dyn FnMut(&str)>),
+}
+
+impl Sink {
+    pub fn writer(writer: impl Write + 'static) -> Self {
+        Sink::Writer(Box::new(writer))
+    }
+
+    pub fn callback(callback: impl FnMut(&str) + 'static) -> Self {
+        Sink::Callback(Box::new(callback))
+    }
+
+    /// Captures the text in memory, the returned handle reads it.
+    ///
+    /// ```
+    /// use lox_rs::{Lox, Sink};
+    ///
+    /// let (sink, buffer) = Sink::buffer();
+    /// let mut lox = Lox::new();
+    /// lox.set_output(sink);
+    /// lox.eval_str("print 1 + 2;").unwrap();
+    /// assert_eq!(*buffer.borrow(), b"3\n");
+    /// ```
+    #[must_use]
+    pub fn buffer() -> (Self, Rc<RefCell<Vec<u8>>>) {
+        let buffer = Rc::new(RefCell::new(vec![]));
+        (Sink::Buffer(buffer.clone()), buffer)
+    }
+
+    /// # Errors
+    ///
+    /// Fails when the underlying writer does.
+    pub fn write(&mut self, text: &str) -> io::Result<()> {
+        match self {
+            Sink::Stdout => io::stdout().write_all(text.as_bytes()),
+            Sink::Stderr => io::stderr().write_all(text.as_bytes()),
+            Sink::Writer(writer) => writer.write_all(text.as_bytes()),
+            Sink::Buffer(buffer) => {
+                buffer.borrow_mut().extend_from_slice(text.as_bytes());
+                Ok(())
+            }
+            Sink::Callback(callback) => {
+                callback(text);
+                Ok(())
+            }
+        }
+    }
+
+    // Colours are only used on a terminal
+    pub(crate) fn is_terminal(&self) -> bool {
+        match self {
+            Sink::Stdout => io::stdout().is_terminal(),
+            Sink::Stderr => io::stderr().is_terminal(),
+            Sink::Writer(_) | Sink::Buffer(_) | Sink::Callback(_) => false,
+        }
+    }
+}
@@ -1,22 +1,70 @@
-use crate::{ast::Expr, scanner::Token};
+use std::rc::Rc;
+
+use crate::{
+    ast::{Expr, Variable},
+    scanner::{Span, Token},
+};
 
 #[derive(Debug)]
 pub enum Stmt {
     Expr(Box<Expr>),
     PrintStmt(PrintStmt),
     Var(Var),
+    Block(Block),
+    If(If),
+    While(While),
+    Function(Rc<Function>),
+    Return(Return),
+    Class(Class),
 }
 
 #[derive(Debug)]
-pub struct ExprStmt;
+pub struct PrintStmt(pub Token<'static>, pub Box<Expr>);
+
+#[derive(Debug)]
+pub struct Var {
+    pub name: Token<'static>,
+    pub initializer: Option<Box<Expr>>,
+}
 
+// The span includes the braces
 #[derive(Debug)]
-pub struct PrintStmt(pub Box<Expr>);
+pub struct Block(pub Vec<Box<Stmt>>, pub Span);
 
 #[derive(Debug)]
-pub struct Var {
-    pub name: Token,
-    pub initializer: Box<Expr>,
+pub struct If {
+    pub keyword: Token<'static>,
+    pub condition: Box<Expr>,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+#[derive(Debug)]
+pub struct While {
+    pub keyword: Token<'static>,
+    pub condition: Box<Expr>,
+    pub body: Box<Stmt>,
+}
+
+// Shared with the runtime function values created from it
+#[derive(Debug)]
+pub struct Function {
+    pub name: Token<'static>,
+    pub params: Vec<Token<'static>>,
+    pub body: Vec<Box<Stmt>>,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: Token<'static>,
+    pub superclass: Option<Variable>,
+    pub methods: Vec<Rc<Function>>,
+}
+
+#[derive(Debug)]
+pub struct Return {
+    pub keyword: Token<'static>,
+    pub value: Option<Box<Expr>>,
 }
 
 impl Stmt {
@@ -25,12 +73,40 @@ impl Stmt {
             Stmt::Expr(expr) => visitor.visit_expr_stmt(expr),
             Stmt::PrintStmt(print_smt) => visitor.visit_print_stmt(print_smt),
             Stmt::Var(var) => visitor.visit_var_stmt(var),
+            Stmt::Block(block) => visitor.visit_block_stmt(block),
+            Stmt::If(if_stmt) => visitor.visit_if_stmt(if_stmt),
+            Stmt::While(while_stmt) => visitor.visit_while_stmt(while_stmt),
+            Stmt::Function(function) => visitor.visit_function_stmt(function),
+            Stmt::Return(return_stmt) => visitor.visit_return_stmt(return_stmt),
+            Stmt::Class(class) => visitor.visit_class_stmt(class),
+        }
+    }
+
+    // Location used to point at the statement, compound statements
+    // only cover their first token
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expr(expr) => expr.span(),
+            Stmt::PrintStmt(PrintStmt(keyword, _)) => keyword.span,
+            Stmt::Var(var) => var.name.span,
+            Stmt::Block(Block(_, span)) => *span,
+            Stmt::If(if_stmt) => if_stmt.keyword.span,
+            Stmt::While(while_stmt) => while_stmt.keyword.span,
+            Stmt::Function(function) => function.name.span,
+            Stmt::Return(return_stmt) => return_stmt.keyword.span,
+            Stmt::Class(class) => class.name.span,
         }
     }
 }
 
 pub trait VisitStmt<T> {
-    fn visit_expr_stmt(&self, stmt: &Box<Expr>) -> T;
+    fn visit_expr_stmt(&self, stmt: &Expr) -> T;
     fn visit_print_stmt(&self, stmt: &PrintStmt) -> T;
     fn visit_var_stmt(&self, stmt: &Var) -> T;
+    fn visit_block_stmt(&self, stmt: &Block) -> T;
+    fn visit_if_stmt(&self, stmt: &If) -> T;
+    fn visit_while_stmt(&self, stmt: &While) -> T;
+    fn visit_function_stmt(&self, stmt: &Rc<Function>) -> T;
+    fn visit_return_stmt(&self, stmt: &Return) -> T;
+    fn visit_class_stmt(&self, stmt: &Class) -> T;
 }
@@ -0,0 +1,170 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    ast::Literal,
+    callable::LoxCallable,
+    class::{LoxClass, LoxInstance},
+};
+
+/// Result of evaluating an expression, functions, classes, instances and
+/// collections are shared by reference.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Num(f64),
+    Str(LoxString),
+    Bool(bool),
+    Nil,
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<LoxInstance>),
+    /// Lists and maps have no syntax, they come from the host.
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<String, Value>>>),
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Num(left), Value::Num(right)) => left == right,
+            (Value::Str(left), Value::Str(right)) => left == right,
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            // Callables are only equal to themselves
+            (Value::Callable(left), Value::Callable(right)) => Rc::ptr_eq(left, right),
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => Rc::ptr_eq(left, right),
+            (Value::List(left), Value::List(right)) => Rc::ptr_eq(left, right),
+            (Value::Map(left), Value::Map(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    #[must_use]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Num(_)
+            | Value::Str(_)
+            | Value::Callable(_)
+            | Value::Class(_)
+            | Value::Instance(_)
+            | Value::List(_)
+            | Value::Map(_) => true,
+            Value::Bool(value) => *value,
+            Value::Nil => false,
+        }
+    }
+
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Num(_) => "number",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "boolean",
+            Value::Nil => "nil",
+            Value::Callable(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+
+    pub(crate) fn try_num(&self) -> Result<f64, String> {
+        match self {
+            Value::Num(num) => Ok(*num),
+            _ => Err(format!("`{self}` NaN")),
+        }
+    }
+
+    pub(crate) fn try_str(&self) -> Result<&str, String> {
+        match self {
+            Value::Str(value) => Ok(value.as_str()),
+            _ => Err(format!("`{self}` Not a String")),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(val) => write!(f, "{val}"),
+            Value::Str(val) => write!(f, "{val}"),
+            Value::Bool(val) => write!(f, "{val}"),
+            Value::Nil => write!(f, "nil"),
+            Value::Callable(callable) => write!(f, "{callable}"),
+            Value::Class(class) => write!(f, "{class}"),
+            Value::Instance(instance) => write!(f, "{instance}"),
+            // Elements aren't printed, a collection can contain itself
+            Value::List(_) => write!(f, "<list>"),
+            Value::Map(_) => write!(f, "<map>"),
+        }
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Num(num) => Value::Num(*num),
+            Literal::Str(text) => Value::Str(LoxString(Repr::Interned(text.clone()))),
+            Literal::Bool(value) => Value::Bool(*value),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+/// Lox string, cloning it never copies the text.
+#[derive(Clone)]
+pub struct LoxString(Repr);
+
+// Constants of the program are interned, two equal ones share their text
+// and comparing them is comparing two pointers. Strings built while running
+// are not, they would live as long as the session.
+#[derive(Clone)]
+enum Repr {
+    Interned(Rc<str>),
+    Heap(Rc<str>),
+}
+
+impl LoxString {
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Interned(text) | Repr::Heap(text) => text,
+        }
+    }
+}
+
+impl PartialEq for LoxString {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Repr::Interned(left), Repr::Interned(right)) if Rc::ptr_eq(left, right) => true,
+            _ => self.as_str() == other.as_str(),
+        }
+    }
+}
+
+impl From<&str> for LoxString {
+    fn from(text: &str) -> Self {
+        LoxString(Repr::Heap(text.into()))
+    }
+}
+
+impl From<String> for LoxString {
+    fn from(text: String) -> Self {
+        LoxString(Repr::Heap(text.into()))
+    }
+}
+
+impl fmt::Debug for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for LoxString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
@@ -0,0 +1,240 @@
+// Runs every `tests/**/*.lox` script through the interpreter binary and
+// compares its behaviour with the annotations found in the script:
+//
+//   // expect: <stdout line>
+//   // expect runtime error: <message>     (exit code 70)
+//   // [line N] Error...  or  // Error...  (exit code 65)
+//
+// `[c line N]` annotations only apply to clox and are ignored.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const EXIT_COMPILE_ERROR: i32 = 65;
+const EXIT_RUNTIME_ERROR: i32 = 70;
+
+// Paths relative to `tests/`, a directory skips every script inside it
+const SKIPPED: &[(&str, &str)] = &[
+    ("benchmark", "too slow for a test run"),
+    ("limit/loop_too_large.lox", "clox specific limit"),
+    ("limit/no_reuse_constants.lox", "clox specific limit"),
+    ("limit/too_many_constants.lox", "clox specific limit"),
+    ("limit/too_many_locals.lox", "clox specific limit"),
+    ("limit/too_many_upvalues.lox", "clox specific limit"),
+    ("expressions", "chapter specific, prints the AST"),
+    ("scanning", "chapter specific, prints the tokens"),
+];
+
+// Scripts that don't behave as the book specifies yet
+const KNOWN_FAILURES: &[(&str, &str)] = &[];
+
+#[derive(Debug, Default)]
+struct Expectations {
+    output: Vec<String>,
+    // (line, message) pairs
+    compile_errors: Vec<(usize, String)>,
+    runtime_error: Option<(usize, String)>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut expectations = Self::default();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            if let Some((_, output)) = line.split_once("// expect: ") {
+                expectations.output.push(output.to_string());
+            } else if let Some((_, message)) = line.split_once("// expect runtime error: ") {
+                expectations.runtime_error = Some((line_number, message.to_string()));
+            } else if let Some((_, error)) = line.split_once("// Error") {
+                expectations
+                    .compile_errors
+                    .push((line_number, error_message(error)));
+            } else if let Some((_, rest)) = line
+                .split_once("// [line ")
+                .or_else(|| line.split_once("// [java line "))
+            {
+                let (number, error) = rest.split_once("] ").expect("Malformed error annotation");
+                let number = number.parse().expect("Malformed error line number");
+                expectations
+                    .compile_errors
+                    .push((number, error_message(error)));
+            }
+        }
+        expectations
+    }
+
+    fn exit_code(&self) -> i32 {
+        if !self.compile_errors.is_empty() {
+            EXIT_COMPILE_ERROR
+        } else if self.runtime_error.is_some() {
+            EXIT_RUNTIME_ERROR
+        } else {
+            0
+        }
+    }
+}
+
+// Keeps only the message of `Error at 'token': message`
+fn error_message(error: &str) -> String {
+    let message = match error.find("': ") {
+        Some(position) => &error[position + 3..],
+        None => error.split_once(": ").map_or(error, |(_, message)| message),
+    };
+    message.to_string()
+}
+
+// (line, message) of every diagnostic, sorted. They read
+//
+//   error: <message>   or   runtime error: <message>
+//    --> path:line:column
+//
+// and an error without a location is at line 0
+fn reported_errors(stderr: &str) -> Vec<(usize, String)> {
+    let mut errors = vec![];
+    let mut lines = stderr.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(message) = line
+            .strip_prefix("error: ")
+            .or_else(|| line.strip_prefix("runtime error: "))
+        else {
+            continue;
+        };
+        let location = lines
+            .peek()
+            .and_then(|next| next.trim_start().strip_prefix("--> "));
+        let line_number = location
+            .and_then(|location| location.rsplit(':').nth(1))
+            .and_then(|number| number.parse().ok())
+            .unwrap_or(0);
+        errors.push((line_number, message.to_string()));
+    }
+    errors.sort();
+    errors
+}
+
+fn run_script(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let expectations = Expectations::parse(&source);
+    let output = Command::new(env!("CARGO_BIN_EXE_lox-rs"))
+        .arg(path)
+        .output()
+        .map_err(|err| err.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    let mut failures = vec![];
+    let lines: Vec<&str> = stdout.lines().collect();
+    if lines != expectations.output {
+        failures.push(format!(
+            "expected output {:?}, got {:?}",
+            expectations.output, lines
+        ));
+    }
+
+    let exit_code = output.status.code().unwrap_or(-1);
+    if exit_code != expectations.exit_code() {
+        failures.push(format!(
+            "expected exit code {}, got {exit_code}",
+            expectations.exit_code()
+        ));
+    }
+
+    let mut expected_errors: Vec<(usize, String)> = expectations
+        .compile_errors
+        .iter()
+        .chain(expectations.runtime_error.iter())
+        .cloned()
+        .collect();
+    expected_errors.sort();
+    let reported_errors = reported_errors(&stderr);
+    if reported_errors != expected_errors {
+        failures.push(format!(
+            "expected errors {expected_errors:?}, got {reported_errors:?}"
+        ));
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("\n    "))
+    }
+}
+
+fn collect_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("Could not read test directory")
+        .map(|entry| entry.expect("Could not read test entry").path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_scripts(&path, scripts);
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            scripts.push(path);
+        }
+    }
+}
+
+#[test]
+fn golden_scripts() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut scripts = vec![];
+    collect_scripts(&root, &mut scripts);
+
+    // directory -> (passed, failed, known failures)
+    let mut summary: BTreeMap<String, (usize, usize, usize)> = BTreeMap::new();
+    let mut failures = vec![];
+    for script in scripts {
+        let relative = script.strip_prefix(&root).expect("Script outside tests/");
+        if SKIPPED
+            .iter()
+            .any(|(skipped, _)| relative.starts_with(skipped))
+        {
+            continue;
+        }
+        let directory = relative
+            .parent()
+            .map(|parent| parent.display().to_string())
+            .filter(|parent| !parent.is_empty())
+            .unwrap_or_else(|| ".".into());
+        let counts = summary.entry(directory).or_default();
+        let known_failure = KNOWN_FAILURES
+            .iter()
+            .any(|(known, _)| relative == Path::new(known));
+        match (run_script(&script), known_failure) {
+            (Ok(()), false) => counts.0 += 1,
+            (Err(_), true) => counts.2 += 1,
+            (Ok(()), true) => {
+                counts.0 += 1;
+                failures.push(format!(
+                    "{}\n    passes now, remove it from KNOWN_FAILURES",
+                    relative.display()
+                ));
+            }
+            (Err(failure), false) => {
+                counts.1 += 1;
+                failures.push(format!("{}\n    {failure}", relative.display()));
+            }
+        }
+    }
+
+    println!(
+        "{:<20} {:>6} {:>6} {:>6}",
+        "directory", "passed", "failed", "known"
+    );
+    for (directory, (passed, failed, known)) in &summary {
+        println!("{directory:<20} {passed:>6} {failed:>6} {known:>6}");
+    }
+    for (skipped, reason) in SKIPPED {
+        println!("{skipped:<20} skipped: {reason}");
+    }
+    assert!(
+        failures.is_empty(),
+        "{} scripts failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}